@@ -0,0 +1,109 @@
+//! Compares the trie lexer with the combined-regex approach it replaced, on `data.math`.
+//!
+//! Run with `cargo bench --bench lexer`.
+
+use std::{hint::black_box, time::{Duration, Instant}};
+
+use amymath::lexer::{Lexer, OperatorToken, GroupCtrlToken, BigOpToken, TokenKind, WordToken};
+use regex::Regex;
+
+const ITERATIONS: u32 = 200;
+const RX_WORD_STR: &str = r"\b[a-zA-Z]+\b";
+const RX_NUMBER_STR: &str = r"[0-9]*\.?[0-9]+";
+
+/// The math lines of the document: everything that isn't blank, a comment, or a directive.
+fn math_lines(document: &str) -> Vec<&str> {
+    let rx_directive = Regex::new(r"^(@|#|(global\s+)?(let|const|fn)\s|undef\s|notation\s)").unwrap();
+    document.lines()
+        .map(|line| line.split('%').next().unwrap())
+        .filter(|line| !line.trim().is_empty() && !rx_directive.is_match(line.trim()))
+        .collect()
+}
+
+/// Every operator and bracket escaped for regex, longest first so that the combined regex prefers them.
+///
+/// Operators made of letters only match as whole words, like `in` in `x in S` but not in `index`.
+fn regex_items() -> Vec<String> {
+    let operators = OperatorToken::sources().iter().map(|token| {
+        let [front_b, back_b] = [
+            token.chars().next(),
+            token.chars().next_back()
+        ].map(|x| if char::is_alphabetic(x.unwrap()) { r"\b" } else { "" });
+        format!("{front_b}{}{back_b}", regex::escape(token))
+    });
+    let brackets = GroupCtrlToken::sources().iter().map(|token| regex::escape(token));
+    let mut tokens: Vec<String> = operators.chain(brackets).collect();
+    tokens.sort_by_key(|x| std::cmp::Reverse(x.len()));
+    tokens
+}
+
+/// The regex lexer: one combined regex finds the tokens, then each match is classified again.
+struct RegexLexer {
+    rx_tokenize: Regex,
+    rx_word: Regex,
+    rx_number: Regex,
+}
+
+impl RegexLexer {
+    fn new() -> Self {
+        let rx_tokenize = Regex::new(&regex_items().into_iter()
+            .chain([RX_WORD_STR.to_string(), RX_NUMBER_STR.to_string()])
+            .collect::<Vec<_>>()
+            .join("|")).unwrap();
+        Self {
+            rx_tokenize,
+            rx_word: Regex::new(RX_WORD_STR).unwrap(),
+            rx_number: Regex::new(RX_NUMBER_STR).unwrap(),
+        }
+    }
+
+    fn tokenize<'doc>(&self, line: &'doc str) -> Vec<TokenKind<'doc>> {
+        self.rx_tokenize.find_iter(line)
+            .filter_map(|token_match| {
+                let token = token_match.as_str();
+                Some(if let Some(op_token) = OperatorToken::try_from(token) {
+                    TokenKind::Operator(op_token)
+                } else if let Some(gc_token) = GroupCtrlToken::try_from(token) {
+                    TokenKind::GroupCtrl(gc_token)
+                } else if self.rx_number.is_match(token) {
+                    TokenKind::Number(token)
+                } else if let Some(bo_token) = BigOpToken::try_from(token) {
+                    TokenKind::BigOp(bo_token)
+                } else if self.rx_word.is_match(token) {
+                    TokenKind::Word(WordToken::from(token))
+                } else {
+                    return None;
+                })
+            })
+            .collect()
+    }
+}
+
+/// Run `pass` repeatedly and print the average time it took. `pass` returns the number of tokens it found.
+fn time(name: &str, mut pass: impl FnMut() -> usize) -> Duration {
+    let start = Instant::now();
+    let mut tokens = 0;
+    for _ in 0..ITERATIONS {
+        tokens += black_box(pass());
+    }
+    let elapsed = start.elapsed() / ITERATIONS;
+    println!("{name:>6}: {elapsed:>10.2?} per pass ({} tokens)", tokens / ITERATIONS as usize);
+    elapsed
+}
+
+fn main() {
+    let document = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/../data.math")).unwrap();
+    let lines = math_lines(&document);
+    println!("lexing {} lines of data.math, {ITERATIONS} times", lines.len());
+
+    let lexer = Lexer::new();
+    let trie = time("trie", || lines.iter()
+        .enumerate()
+        .map(|(n, line)| lexer.tokenize(line, 0, n + 1).0.len())
+        .sum());
+
+    let regex_lexer = RegexLexer::new();
+    let regex = time("regex", || lines.iter().map(|line| regex_lexer.tokenize(line).len()).sum());
+
+    println!("trie lexer is {:.1}x the speed of the regex lexer", regex.as_secs_f64() / trie.as_secs_f64());
+}
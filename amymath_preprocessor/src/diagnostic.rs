@@ -0,0 +1,297 @@
+use crate::{lexer::span::{FileId, Span}, source_map::SourceMap};
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The document cannot be processed.
+    Error,
+    /// The document can be processed, but probably not the way the author intended.
+    Warning,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A region of the source document with a message attached.
+#[derive(Debug, Clone)]
+pub struct Label {
+    /// The region being pointed at.
+    pub span: Span,
+    /// What to say about the region. May be empty.
+    pub message: String,
+    /// Primary labels mark the cause of the problem and are underlined with `^`.
+    /// Secondary labels mark related locations and are underlined with `-`.
+    pub is_primary: bool,
+}
+
+/// A message about the source document that can be rendered with excerpts of the source.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    /// How serious the problem is.
+    pub severity: Severity,
+    /// A short description of the problem.
+    pub message: String,
+    /// The locations in the source relevant to the problem.
+    pub labels: Vec<Label>,
+    /// Extra context about the problem.
+    pub notes: Vec<String>,
+    /// A suggestion for how to fix the problem.
+    pub help: Option<String>,
+}
+
+/// The object can be described as a [`Diagnostic`].
+pub trait ToDiagnostic {
+    /// Describe the object as a diagnostic.
+    fn to_diagnostic(&self) -> Diagnostic;
+}
+
+impl Diagnostic {
+    /// Construct a diagnostic with no labels.
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            message: message.into(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: None,
+        }
+    }
+
+    /// Construct an error diagnostic with no labels.
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message)
+    }
+
+    /// Construct a warning diagnostic with no labels.
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, message)
+    }
+
+    /// Point at the cause of the problem.
+    pub fn with_primary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label { span, message: message.into(), is_primary: true });
+        self
+    }
+
+    /// Point at a location related to the problem.
+    pub fn with_secondary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label { span, message: message.into(), is_primary: false });
+        self
+    }
+
+    /// Add context about the problem.
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Suggest a fix for the problem.
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// The label the diagnostic is reported at, if any.
+    pub fn primary_span(&self) -> Option<Span> {
+        self.labels.iter()
+            .find(|label| label.is_primary)
+            .or(self.labels.first())
+            .map(|label| label.span)
+    }
+
+    /// Render the diagnostic in the style of rustc, quoting the lines of `sources` that the labels point at.
+    ///
+    /// Problems in imported files are followed by the chain of `@import` directives that led to them.
+    pub fn render(&self, sources: &SourceMap) -> String {
+        let location = |span: Span| format!("{}:{}:{}",
+            sources.file(span.file).name,
+            span.line,
+            span.column(sources.line(span.file, span.line)),
+        );
+
+        let primary_file = self.primary_span().map_or(0, |span| span.file);
+        // The primary file first, then any others
+        let mut label_lines: Vec<(bool, FileId, usize)> = self.labels.iter()
+            .map(|label| (label.span.file != primary_file, label.span.file, label.span.line))
+            .collect();
+        label_lines.sort();
+        label_lines.dedup();
+        let gutter = label_lines.iter().map(|(.., n)| n.to_string().len()).max().unwrap_or(0);
+        let pad = " ".repeat(gutter);
+
+        let mut out = format!("{}: {}\n", self.severity, self.message);
+
+        if let Some(primary) = self.primary_span() {
+            let mut current_file = None;
+            for (_, file, line_number) in label_lines {
+                if current_file != Some(file) {
+                    let (arrow, first) = match current_file {
+                        None => ("-->", primary),
+                        Some(_) => (":::", self.labels.iter().find(|label| label.span.file == file).unwrap().span),
+                    };
+                    out += &format!("{pad}{arrow} {}\n", location(first));
+                    out += &format!("{pad} |\n");
+                    current_file = Some(file);
+                }
+                let line = sources.line(file, line_number).replace('\t', " ");
+                let labels: Vec<&Label> = self.labels.iter()
+                    .filter(|label| (label.span.file, label.span.line) == (file, line_number))
+                    .collect();
+                out += &format!("{line_number:>gutter$} | {line}\n");
+                for row in render_underlines(&line, &labels) {
+                    out += &format!("{pad} | {}\n", row.trim_end());
+                }
+            }
+        }
+
+        let import_notes = self.primary_span().into_iter()
+            .flat_map(|span| sources.import_chain(span.file))
+            .map(|import| format!("imported from {}", location(import)));
+        let mut notes = self.notes.iter().cloned().chain(import_notes).peekable();
+        if (notes.peek().is_some() || self.help.is_some()) && !self.labels.is_empty() {
+            out += &format!("{pad} |\n");
+        }
+        for note in notes {
+            out += &format!("{pad} = note: {note}\n");
+        }
+        if let Some(help) = &self.help {
+            out += &format!("{pad} = help: {help}\n");
+        }
+
+        out
+    }
+}
+
+/// Build the rows drawn beneath a quoted source line, underlining each label and attaching its message.
+///
+/// The rightmost message is written on the underline row itself; the rest hang below it on connector lines.
+fn render_underlines(line: &str, labels: &[&Label]) -> Vec<String> {
+    // (column, width, label) in characters rather than bytes
+    let mut marks: Vec<(usize, usize, &Label)> = labels.iter()
+        .map(|label| {
+            let column = label.span.column(line) - 1;
+            let width = line.get(label.span.start..label.span.end.min(line.len()))
+                .map_or(0, |text| text.chars().count())
+                .max(1);
+            (column, width, *label)
+        })
+        .collect();
+    // Rightmost first
+    marks.sort_by_key(|(column, ..)| std::cmp::Reverse(*column));
+
+    let mut underline = Vec::<char>::new();
+    // Secondary first so that primary underlines are drawn over them
+    for (column, width, label) in marks.iter().rev().filter(|(.., label)| !label.is_primary)
+        .chain(marks.iter().rev().filter(|(.., label)| label.is_primary))
+    {
+        let end = column + width;
+        if underline.len() < end {
+            underline.resize(end, ' ');
+        }
+        let ch = if label.is_primary { '^' } else { '-' };
+        underline[*column..end].fill(ch);
+    }
+
+    let mut messages = marks.iter().filter(|(.., label)| !label.message.is_empty());
+    let mut rows = vec![underline.iter().collect::<String>()];
+    if let Some((.., label)) = messages.next() {
+        rows[0] += &format!(" {}", label.message);
+    }
+
+    let hanging: Vec<_> = messages.collect();
+    for (i, (column, _, label)) in hanging.iter().enumerate() {
+        let lefter = &hanging[(i + 1)..];
+        let mut connector = Vec::<char>::new();
+        for col in std::iter::once(column).chain(lefter.iter().map(|(column, ..)| column)) {
+            if connector.len() <= *col {
+                connector.resize(col + 1, ' ');
+            }
+            connector[*col] = '|';
+        }
+        rows.push(connector.iter().collect());
+
+        let mut message_row = connector;
+        message_row.truncate(*column);
+        rows.push(format!("{}{}", message_row.iter().collect::<String>(), label.message));
+    }
+
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn sources() -> SourceMap {
+        let mut sources = SourceMap::new(PathBuf::from("main.math"), "let x be in Real\n@import defs.math\n".to_string());
+        sources.add(PathBuf::from("defs.math"), "const c\nx + (y\ny)\n".to_string(), Span::new(0, 2, 0, 17));
+        sources
+    }
+
+    #[test]
+    fn primary_and_secondary() {
+        let diagnostic = Diagnostic::warning("`x` was already defined")
+            .with_primary(Span::new(0, 1, 4, 5), "defined again here")
+            .with_secondary(Span::new(0, 1, 0, 3), "as a variable")
+            .with_help("use a different name");
+        assert_eq!(diagnostic.render(&sources()), "\
+warning: `x` was already defined
+ --> main.math:1:5
+  |
+1 | let x be in Real
+  | --- ^ defined again here
+  | |
+  | as a variable
+  |
+  = help: use a different name
+");
+    }
+
+    #[test]
+    fn spanning_lines() {
+        let diagnostic = Diagnostic::error("Unclosed bracket")
+            .with_primary(Span::new(1, 2, 4, 5), "never closed")
+            .with_secondary(Span::new(1, 3, 1, 2), "closes nothing");
+        assert_eq!(diagnostic.render(&sources()), "\
+error: Unclosed bracket
+ --> defs.math:2:5
+  |
+2 | x + (y
+  |     ^ never closed
+3 | y)
+  |  - closes nothing
+  |
+  = note: imported from main.math:2:1
+");
+    }
+
+    #[test]
+    fn imported_file() {
+        let diagnostic = Diagnostic::warning("Unused constant")
+            .with_primary(Span::new(1, 1, 6, 7), "")
+            .with_secondary(Span::new(0, 2, 0, 7), "imported here")
+            .with_note("constants are only checked within their file");
+        assert_eq!(diagnostic.render(&sources()), "\
+warning: Unused constant
+ --> defs.math:1:7
+  |
+1 | const c
+  |       ^
+ ::: main.math:2:1
+  |
+2 | @import defs.math
+  | ------- imported here
+  |
+  = note: constants are only checked within their file
+  = note: imported from main.math:2:1
+");
+    }
+}
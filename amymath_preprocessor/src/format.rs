@@ -0,0 +1,43 @@
+/// Tidy the layout of a document without changing what it means.
+///
+/// - Indentation and trailing whitespace are removed.
+/// - A comment after code is separated from it by one space.
+/// - Headings get a blank line on either side.
+/// - Runs of blank lines become a single blank line, and the document ends with exactly one newline.
+pub fn format_document(document: &str) -> String {
+    let mut lines = Vec::<String>::new();
+    let mut blank_pending = false;
+    let mut after_heading = false;
+
+    for line in document.lines() {
+        let (code, comment) = match line.find('%') {
+            Some(comment_start) => (line[..comment_start].trim(), Some(line[comment_start..].trim_end())),
+            None => (line.trim(), None),
+        };
+
+        let line = match (code.is_empty(), comment) {
+            (true, None) => {
+                blank_pending = true;
+                continue;
+            },
+            (true, Some(comment)) => comment.to_string(),
+            (false, None) => code.to_string(),
+            (false, Some(comment)) => format!("{code} {comment}"),
+        };
+
+        let is_heading = code.starts_with('#');
+        if !lines.is_empty() && (blank_pending || after_heading || is_heading) {
+            lines.push(String::new());
+        }
+        lines.push(line);
+        blank_pending = false;
+        after_heading = is_heading;
+    }
+
+    if lines.is_empty() {
+        return String::new();
+    }
+    let mut formatted = lines.join("\n");
+    formatted.push('\n');
+    formatted
+}
@@ -0,0 +1,112 @@
+use crate::to_tex::ToTex;
+use super::operator::OperatorToken;
+
+macro_rules! big_op_tokens {
+    {
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $($form:ident {
+                $(
+                    $(#[$token_meta:meta])*
+                    $($src_token:literal)|+ => $token:ident => $out_tex:literal,
+                )*
+            },)*
+        }
+    } => {
+        $(#[$meta])*
+        $vis enum $name {
+            $($(
+                $(#[$token_meta])*
+                $token,
+            )*)*
+        }
+
+        impl $name {
+            /// Try to construct a big operator token. If `None`, the word is not a big operator.
+            pub fn try_from(token: &str) -> Option<Self> {
+                match token {
+                    $($($(
+                        $src_token)|* => Some(Self::$token),
+                    )*)*
+                    _ => None,
+                }
+            }
+
+            /// The preferred string for writing this operator in the source document.
+            pub fn source_str(&self) -> &'static str {
+                match self {
+                    $($(
+                        Self::$token => [$( $src_token ),*][0],
+                    )*)*
+                }
+            }
+
+            /// How the operator uses its index group.
+            pub fn form(&self) -> BigOpForm {
+                match self {
+                    $(
+                        $(Self::$token)|* => BigOpForm::$form,
+                    )*
+                }
+            }
+
+            /// The TeX command for the operator.
+            pub fn command(&self) -> &'static str {
+                match self {
+                    $($(
+                        Self::$token => $out_tex,
+                    )*)*
+                }
+            }
+        }
+    };
+}
+
+/// How a [`BigOpToken`] uses the `[...]` group written after it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BigOpForm {
+    /// The index is the degree of a root, like `\sqrt[n]{x}`.
+    Root,
+    /// The index gives the bounds the operator iterates over, like `\sum_{i=0}^{n}`.
+    Bounds,
+}
+
+big_op_tokens!{
+    /// An operator written before its operand, which may be given an index or bounds in `[...]`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum BigOpToken {
+        Root {
+            /// Square (or n) root
+            "sqrt" => Sqrt => r"\sqrt",
+        },
+        Bounds {
+            /// Summation
+            "sum" => Sum => r"\sum",
+            /// Production
+            "prod" => Prod => r"\prod",
+            /// Union of many sets.
+            /// Can also be written as `union` when not between two sets.
+            "bigcup" => Union => r"\bigcup",
+            /// Intersection of many sets.
+            /// Can also be written as `intersection` when not between two sets.
+            "bigcap" => Intersection => r"\bigcap",
+        },
+    }
+}
+
+impl BigOpToken {
+    /// The big operator that shares its name with an infix operator, like `union` in `A union B`.
+    pub fn from_operator(op_token: &OperatorToken) -> Option<Self> {
+        match op_token {
+            OperatorToken::Union => Some(Self::Union),
+            OperatorToken::Intersection => Some(Self::Intersection),
+            _ => None,
+        }
+    }
+}
+
+impl ToTex for BigOpToken {
+    fn to_tex(self) -> String {
+        self.command().to_string()
+    }
+}
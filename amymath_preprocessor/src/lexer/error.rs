@@ -1,5 +1,8 @@
 use std::error::Error;
 
+use crate::diagnostic::{Diagnostic, ToDiagnostic};
+use super::span::Span;
+
 /// An error that occurs while tokenizing a document.
 #[derive(Debug)]
 pub enum LexerError {
@@ -7,14 +10,54 @@ pub enum LexerError {
     UnknownToken{
         /// The token from the source document.
         token: String,
+        /// Where the token was found.
+        span: Span,
+    },
+    /// A character that cannot start any token was left out.
+    SkippedChar{
+        /// The character from the source document.
+        token: String,
+        /// Where the character was found.
+        span: Span,
     },
 }
 
 impl std::fmt::Display for LexerError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            LexerError::UnknownToken{ token }
+            LexerError::UnknownToken{ token, .. }
                 => write!(f, "Unrecognized token: `{token}`"),
+            LexerError::SkippedChar{ token, .. }
+                => write!(f, "Skipped unrecognized character: `{token}`"),
+        }
+    }
+}
+
+impl LexerError {
+    /// Whether the problem still allows the line to be processed.
+    pub fn is_warning(&self) -> bool {
+        matches!(self, LexerError::SkippedChar{ .. })
+    }
+
+    /// Where in the source document the error occurred.
+    pub fn span(&self) -> Span {
+        match self {
+            LexerError::UnknownToken{ span, .. } => *span,
+            LexerError::SkippedChar{ span, .. } => *span,
+        }
+    }
+}
+
+impl ToDiagnostic for LexerError {
+    fn to_diagnostic(&self) -> Diagnostic {
+        match self {
+            LexerError::UnknownToken{ token, span }
+                => Diagnostic::error(self.to_string())
+                    .with_primary(*span, format!("`{token}` is not an operator, bracket, number, or word")),
+            LexerError::SkippedChar{ token, span }
+                => Diagnostic::warning(self.to_string())
+                    .with_primary(*span, format!("`{token}` is left out of the output"))
+                    .with_help("unrecognized characters can instead be copied into the output as they are"),
         }
     }
 }
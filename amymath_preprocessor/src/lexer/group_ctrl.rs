@@ -1,4 +1,7 @@
+use std::sync::Arc;
+
 use crate::to_tex::ToTex;
+use super::notation::CustomBrackets;
 
 macro_rules! group_ctrl_tokens {
     {
@@ -20,6 +23,8 @@ macro_rules! group_ctrl_tokens {
                 $(#[$kind_variant_meta])*
                 $kind,
             )*
+            /// Brackets added by the document with the `notation` directive
+            Custom(Arc<CustomBrackets>),
         }
 
         $(#[$token_meta])*
@@ -31,13 +36,9 @@ macro_rules! group_ctrl_tokens {
         }
 
         impl $token_name {
-            /// A list of the tokens, escaped for regex.
-            pub fn regex_items() -> Vec<String> {
-                let mut tokens = [
-                    $($src_open, $src_close,)*
-                ].into_iter().map(regex::escape).collect::<Vec<_>>();
-                tokens.sort_by(|a, b| b.len().cmp(&a.len()));
-                tokens
+            /// Every string that is a builtin delimiter in the source document.
+            pub fn sources() -> &'static [&'static str] {
+                &[$($src_open, $src_close,)*]
             }
 
             /// Try to construct a delimiter token. Returns `None` if the token is not a delimiter.
@@ -56,13 +57,15 @@ macro_rules! group_ctrl_tokens {
             }
 
             /// The string that would be used in the source document to represent this delimiter.
-            pub fn source_str(&self) -> &'static str {
+            pub fn source_str(&self) -> &str {
                 match self.ctrl {
-                    GroupControl::Open => match self.kind {
+                    GroupControl::Open => match &self.kind {
                         $($kind_name::$kind => $src_open,)*
+                        $kind_name::Custom(brackets) => &brackets.open,
                     },
-                    GroupControl::Close => match self.kind {
+                    GroupControl::Close => match &self.kind {
                         $($kind_name::$kind => $src_close,)*
+                        $kind_name::Custom(brackets) => &brackets.close,
                     },
                 }
             }
@@ -72,13 +75,15 @@ macro_rules! group_ctrl_tokens {
             fn to_tex(self) -> String {
                 match self.ctrl {
                     GroupControl::Open => format!($open_fmt,
-                        match self.kind {
+                        match &self.kind {
                             $($kind_name::$kind => $out_open,)*
+                            $kind_name::Custom(brackets) => &brackets.open_tex,
                         }
                     ),
                     GroupControl::Close => format!($close_fmt,
-                        match self.kind {
+                        match &self.kind {
                             $($kind_name::$kind => $out_close,)*
+                            $kind_name::Custom(brackets) => &brackets.close_tex,
                         }
                     ),
                 }
@@ -98,13 +103,13 @@ pub enum GroupControl {
 
 group_ctrl_tokens!{
     /// What pairing the token represents.
-    #[derive(Debug, Clone, Copy)]
+    #[derive(Debug, Clone, PartialEq, Eq)]
     pub enum BracketKind;
 
     shared_format = (r"{{\br{{{}}}{{", "}}{{{}}}}}");
 
     /// A delimiter marking the start or end of a subexpression.
-    #[derive(Debug, Clone, Copy)]
+    #[derive(Debug, Clone)]
     pub struct GroupCtrlToken {
         /// Parentheses `( ... )`
         Paren  (  "(", ")"  ) => (r"\lparen", r"\rparen"),
@@ -140,7 +145,7 @@ impl BracketKind {
             | (BracketKind::Brace, BracketKind::Brace)
             | (BracketKind::VVert, BracketKind::VVert)
             | (BracketKind::Vert,  BracketKind::Vert )
-        )
+        ) || matches!((self, other), (BracketKind::Custom(open), BracketKind::Custom(close)) if open == close)
     }
 }
 
@@ -1,4 +1,5 @@
-use regex::Regex;
+use word::direct::{DirectWordToken, split_subscript};
+use trie::Trie;
 
 /// Operator tokens.
 pub mod operator;
@@ -6,81 +7,392 @@ pub mod operator;
 pub mod group_ctrl;
 /// Variable/constant/function tokens.
 pub mod word;
+/// Big operator tokens.
+pub mod big_op;
 /// Generic Token type.
 pub mod token;
 /// Lexer error module.
 pub mod error;
+/// Source location module.
+pub mod span;
+/// Operators, words, and brackets added by the document.
+pub mod notation;
+/// Prefix tree for matching operators and brackets.
+pub(crate) mod trie;
 
 pub use operator::*;
 pub use group_ctrl::*;
 pub use word::*;
+pub use big_op::*;
 pub use token::*;
 pub use error::LexerError;
+pub use span::{Span, FileId};
+pub use notation::Notation;
+
+/// What the lexer does with characters that can't start any token, like `$` or `?`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+pub enum UnknownChars {
+    /// Report each character as an error.
+    #[default]
+    Deny,
+    /// Report each character as a warning, and leave it out of the output.
+    Warn,
+    /// Copy the characters into the output as [`TokenKind::Verbatim`] tokens, escaping any that are special to TeX.
+    Passthrough,
+}
 
 /// The machine that breaks a document string into tokens.
 pub struct Lexer {
-    rx_word: Regex,
-    rx_number: Regex,
-    rx_tokenize: Regex,
+    /// Every operator and bracket, builtin and added by the document.
+    symbols: Trie<TokenKind<'static>>,
+    notation: Notation,
+    unknown_chars: UnknownChars,
+}
+
+impl Default for Lexer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether `c` counts as part of a word when deciding if a symbol made of letters stands on its own.
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// The length in bytes of the word that `text` starts with, like `x`, `x1`, or `v_max`.
+///
+/// A word is a run of letters, optionally followed by a subscript: digits, or `_` and two or more letters.
+/// A single letter or digits after `_`, as in `x_i` or `x_0`, are left to the subscript operator,
+/// since they are usually an index rather than a label, and `x_0` would otherwise be a second name for `x0`.
+/// Big operators like `sum` never take a subscript this way, so that `sum_10` still reads as a bound.
+fn word_len(text: &str) -> Option<usize> {
+    let run = |text: &str, f: fn(&char) -> bool| text.chars().take_while(f).count();
+    let letters = run(text, char::is_ascii_alphabetic);
+    if letters == 0 {
+        return None;
+    }
+    if BigOpToken::try_from(&text[..letters]).is_some() {
+        return Some(letters);
+    }
+    let rest = &text[letters..];
+    let subscript = match rest.strip_prefix('_') {
+        Some(rest) => match run(rest, char::is_ascii_alphabetic) {
+            letters if letters >= 2 => 1 + letters,
+            _ => 0,
+        },
+        None => run(rest, char::is_ascii_digit),
+    };
+    Some(letters + subscript)
+}
+
+/// The length in bytes of the number that `text` starts with, like `12`, `3.5`, or `.5`.
+fn number_len(text: &str) -> Option<usize> {
+    let digits = |text: &str| text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let whole = digits(text);
+    let fraction = text[whole..].strip_prefix('.').map_or(0, digits);
+    match (whole, fraction) {
+        (0, 0) => None,
+        (whole, 0) => Some(whole),
+        (whole, fraction) => Some(whole + 1 + fraction),
+    }
 }
 
 impl Lexer {
-    /// Constructs a new lexer, initializing the regex functions.
-    ///
-    /// ...Because regex can't be precompiled in Rust :/
+    /// Constructs a new lexer with only the builtin notation.
     pub fn new() -> Self {
-        const RX_WORD_STR: &str = r"\b[a-zA-Z]+\b";
-        let rx_word = Regex::new(RX_WORD_STR).unwrap();
-
-        const RX_NUMBER_STR: &str = r"[0-9]*\.?[0-9]+";
-        let rx_number = Regex::new(RX_NUMBER_STR).unwrap();
-
-        // Combine all regex patterns into one
-        let operators = OperatorToken::regex_items();
-        let group_ctrls = GroupCtrlToken::regex_items();
-        let mut special = operators.iter()
-            .chain(group_ctrls.iter())
-            .map(|x| x.as_str())
-            .collect::<Vec<_>>();
-        special.sort_by(|a, b| b.len().cmp(&a.len()));
-        let special = special;
-        let rx_tokenize = Regex::new(
-            special.into_iter()
-                .chain([RX_WORD_STR].into_iter())
-                .chain([RX_NUMBER_STR].into_iter())
-                .collect::<Vec<_>>()
-                .join("|")
-                .as_str()
-        ).unwrap();
-
+        let notation = Notation::default();
         Self {
-            rx_word,
-            rx_number,
-            rx_tokenize,
+            symbols: Self::symbols(&notation),
+            notation,
+            unknown_chars: UnknownChars::default(),
         }
     }
 
-    /// Break a document string into tokens.
-    pub fn tokenize<'doc>(&'_ self, line: &'doc str) -> Result<Vec<Token<'doc>>, LexerError> {
-        let tokens = self.rx_tokenize
-            .find_iter(line)
-            .map(|token_match| {
-                let token_str: &'doc str = token_match.as_str();
-                if let Some(op_token) = OperatorToken::try_from(token_str) {
-                    Ok(Token::Operator(op_token))
-                } else if let Some(gc_token) = GroupCtrlToken::try_from(&token_str) {
-                    Ok(Token::GroupCtrl(gc_token))
-                } else if self.rx_number.is_match(&token_str) {
-                    Ok(Token::Number(token_str))
-                } else if self.rx_word.is_match(&token_str) {
-                    Ok(Token::Word(WordToken::from(token_str)))
-                } else {
-                    Err(LexerError::UnknownToken { token: token_str.to_string() })
+    /// Choose what happens to characters that can't start any token.
+    pub fn unknown_chars(mut self, unknown_chars: UnknownChars) -> Self {
+        self.unknown_chars = unknown_chars;
+        self
+    }
+
+    /// Build the trie of every operator and bracket.
+    fn symbols(notation: &Notation) -> Trie<TokenKind<'static>> {
+        let mut symbols = Trie::new();
+        for source in GroupCtrlToken::sources() {
+            symbols.insert(source, TokenKind::GroupCtrl(GroupCtrlToken::try_from(source).unwrap()));
+        }
+        // Operators are added after brackets so that they take priority, as they do in `try_from`
+        for source in OperatorToken::sources() {
+            symbols.insert(source, TokenKind::Operator(OperatorToken::try_from(source).unwrap()));
+        }
+        for op in &notation.operators {
+            symbols.insert(&op.source, TokenKind::Operator(OperatorToken::Custom(op.clone())));
+        }
+        for brackets in &notation.brackets {
+            let kind = BracketKind::Custom(brackets.clone());
+            symbols.insert(&brackets.open, TokenKind::GroupCtrl(GroupCtrlToken::open(kind.clone())));
+            symbols.insert(&brackets.close, TokenKind::GroupCtrl(GroupCtrlToken::close(kind)));
+        }
+        symbols
+    }
+
+    /// The notation the document has added so far.
+    pub fn notation(&self) -> &Notation {
+        &self.notation
+    }
+
+    /// Add notation to the language for the rest of the document.
+    pub fn extend_notation(&mut self, extend: impl FnOnce(&mut Notation)) {
+        extend(&mut self.notation);
+        self.symbols = Self::symbols(&self.notation);
+    }
+
+    /// Categorize a word, looking up notation by the word's base so that it keeps any subscript.
+    fn word<'doc>(&'doc self, word: &'doc str) -> TokenKind<'doc> {
+        if let Some(bo_token) = BigOpToken::try_from(word) {
+            TokenKind::BigOp(bo_token)
+        } else if let Some(custom) = self.notation.word(split_subscript(word).0) {
+            TokenKind::Word(WordToken::Direct(DirectWordToken {
+                name: word,
+                kind: Some(custom.kind),
+                command: Some(&custom.tex),
+            }))
+        } else {
+            TokenKind::Word(WordToken::from(word))
+        }
+    }
+
+    /// Break a line of the document into tokens.
+    ///
+    /// Each position takes the longest operator or bracket that starts there, then a word, then a number.
+    /// Operators made of letters, like `in`, only count when they aren't part of a longer word, like `index`.
+    ///
+    /// Every character that can't start a token is handled according to [`UnknownChars`].
+    /// Whatever is reported is returned alongside the tokens: errors for [`UnknownChars::Deny`], warnings for [`UnknownChars::Warn`].
+    ///
+    /// `file` and `line_number` are recorded in the [`Span`] of each token.
+    /// Tokens for words added with `notation` borrow their TeX from the lexer.
+    pub fn tokenize<'doc>(&'doc self, line: &'doc str, file: FileId, line_number: usize) -> (Vec<Token<'doc>>, Vec<LexerError>) {
+        let mut tokens = Vec::<Token<'doc>>::new();
+        let mut errors = Vec::new();
+        let mut start = 0;
+
+        while let Some(c) = line[start..].chars().next() {
+            let rest = &line[start..];
+            let after_word = line[..start].chars().next_back().is_some_and(is_word_char);
+            let symbol = self.symbols.longest_match(rest, |len, _| {
+                let symbol = &rest[..len];
+                let joins_before = symbol.starts_with(char::is_alphabetic) && after_word;
+                let joins_after = symbol.ends_with(char::is_alphabetic) && rest[len..].starts_with(is_word_char);
+                !joins_before && !joins_after
+            });
+
+            let (len, kind) = if let Some((len, kind)) = symbol {
+                (len, kind.clone())
+            } else if let Some(len) = word_len(rest) {
+                (len, self.word(&rest[..len]))
+            } else if let Some(len) = number_len(rest) {
+                (len, TokenKind::Number(&rest[..len]))
+            } else if c.is_whitespace() {
+                start += c.len_utf8();
+                continue;
+            } else {
+                let span = Span::new(file, line_number, start, start + c.len_utf8());
+                let token = c.to_string();
+                match self.unknown_chars {
+                    UnknownChars::Deny => errors.push(LexerError::UnknownToken { token, span }),
+                    UnknownChars::Warn => errors.push(LexerError::SkippedChar { token, span }),
+                    // Unknown characters next to each other are kept together
+                    UnknownChars::Passthrough => match tokens.last_mut() {
+                        Some(Token { kind: TokenKind::Verbatim(text), span: prev_span }) if prev_span.end == start => {
+                            *text = &line[prev_span.start..span.end];
+                            prev_span.end = span.end;
+                        },
+                        _ => tokens.push(Token::new(TokenKind::Verbatim(&line[span.start..span.end]), span)),
+                    },
                 }
-            })
-            .collect::<Result<_, _>>()?;
+                start += c.len_utf8();
+                continue;
+            };
+
+            tokens.push(Token::new(kind, Span::new(file, line_number, start, start + len)));
+            start += len;
+        }
 
-        Ok(tokens)
+        (tokens, errors)
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use crate::to_tex::ToTex;
+    use super::*;
+
+    /// Each token on the line as its category and the text it was lexed from, like `number 0`.
+    fn lex(lexer: &Lexer, line: &str) -> Vec<String> {
+        let (tokens, errors) = lexer.tokenize(line, 0, 1);
+        assert!(errors.is_empty(), "{line:?} should lex without errors");
+        tokens.iter()
+            .map(|token| {
+                let category = match token.kind {
+                    TokenKind::Word(_) => "word",
+                    TokenKind::Number(_) => "number",
+                    TokenKind::Operator(_) => "op",
+                    TokenKind::BigOp(_) => "bigop",
+                    TokenKind::GroupCtrl(_) => "bracket",
+                    TokenKind::Verbatim(_) => "verbatim",
+                };
+                format!("{category} {}", &line[token.span.start..token.span.end])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn numbers_before_ranges() {
+        let lexer = Lexer::new();
+        assert_eq!(lex(&lexer, "0..n"), ["number 0", "op ..", "word n"]);
+        assert_eq!(lex(&lexer, "0..=n"), ["number 0", "op ..=", "word n"]);
+        assert_eq!(lex(&lexer, "0..<n"), ["number 0", "op ..<", "word n"]);
+        assert_eq!(lex(&lexer, "1..10"), ["number 1", "op ..", "number 10"]);
+        assert_eq!(lex(&lexer, "0.5..2.25"), ["number 0.5", "op ..", "number 2.25"]);
+        assert_eq!(lex(&lexer, "3.14"), ["number 3.14"]);
+    }
+
+    #[test]
+    fn longest_operator_wins() {
+        let lexer = Lexer::new();
+        assert_eq!(lex(&lexer, "a..b"), ["word a", "op ..", "word b"]);
+        assert_eq!(lex(&lexer, "a..=b"), ["word a", "op ..=", "word b"]);
+        assert_eq!(lex(&lexer, "a..<b"), ["word a", "op ..<", "word b"]);
+        assert_eq!(lex(&lexer, "a<=b"), ["word a", "op <=", "word b"]);
+        assert_eq!(lex(&lexer, "a< =b"), ["word a", "op <", "op =", "word b"]);
+        assert_eq!(lex(&lexer, "a=>b"), ["word a", "op =>", "word b"]);
+        assert_eq!(lex(&lexer, "a<=>b"), ["word a", "op <=>", "word b"]);
+    }
+
+    #[test]
+    fn word_and_number_boundaries() {
+        let lexer = Lexer::new();
+        assert_eq!(lex(&lexer, "x in index"), ["word x", "op in", "word index"]);
+        assert_eq!(lex(&lexer, "inx"), ["word inx"]);
+        assert_eq!(lex(&lexer, "2x"), ["number 2", "word x"]);
+        assert_eq!(lex(&lexer, "x2y"), ["word x2", "word y"]);
+        assert_eq!(lex(&lexer, "12.5.5"), ["number 12.5", "number .5"]);
+        // Two or more letters after `_` are part of the word
+        assert_eq!(lex(&lexer, "x_in y"), ["word x_in", "word y"]);
+        assert_eq!(lex(&lexer, "x_i in y"), ["word x", "op _", "word i", "op in", "word y"]);
+    }
+
+    #[test]
+    fn words_with_subscripts() {
+        let lexer = Lexer::new();
+        let tex = |line| {
+            let (tokens, _) = lexer.tokenize(line, 0, 1);
+            assert_eq!(tokens.len(), 1, "{line:?} should be one word");
+            tokens[0].kind.clone().to_tex()
+        };
+        assert_eq!(tex("x1"), "{x_{1}}");
+        assert_eq!(tex("v_max"), r"{v_{\text{max}}}");
+        // Builtin words keep their kind under a subscript
+        assert_eq!(tex("theta0"), r"\var{\theta_{0}}");
+        assert_eq!(tex("theta_max"), r"\var{\theta_{\text{max}}}");
+
+        // An index is left to the subscript operator
+        assert_eq!(lex(&lexer, "x_0"), ["word x", "op _", "number 0"]);
+        assert_eq!(lex(&lexer, "x_i"), ["word x", "op _", "word i"]);
+        assert_eq!(lex(&lexer, "sum_10"), ["bigop sum", "op _", "number 10"]);
+    }
+
+    /// Each error on the line as the character it reports, the byte range of its span, and its column.
+    fn errors(lexer: &Lexer, line: &str) -> Vec<(String, usize, usize, usize)> {
+        lexer.tokenize(line, 0, 1).1.into_iter()
+            .map(|error| {
+                let (token, span) = match error {
+                    LexerError::UnknownToken { token, span } | LexerError::SkippedChar { token, span } => (token, span),
+                };
+                (token, span.start, span.end, span.column(line))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn unknown_chars() {
+        let deny = Lexer::new();
+        assert_eq!(errors(&deny, "x ? y"), [("?".to_string(), 2, 3, 3)]);
+        assert!(deny.tokenize("x ? y", 0, 1).1.iter().all(|error| !error.is_warning()));
+        // Spans are in bytes and columns in characters
+        assert_eq!(errors(&deny, "é + ü"), [("é".to_string(), 0, 2, 1), ("ü".to_string(), 5, 7, 5)]);
+
+        let warn = Lexer::new().unknown_chars(UnknownChars::Warn);
+        assert_eq!(errors(&warn, "x ? y"), [("?".to_string(), 2, 3, 3)]);
+        assert!(warn.tokenize("x ? y", 0, 1).1.iter().all(LexerError::is_warning));
+        // Skipped characters leave no token behind
+        assert_eq!(warn.tokenize("x ? y", 0, 1).0.len(), 2);
+
+        let passthrough = Lexer::new().unknown_chars(UnknownChars::Passthrough);
+        // Neighbouring characters make one token
+        assert_eq!(lex(&passthrough, "x ?é$ y ?"), ["word x", "verbatim ?é$", "word y", "verbatim ?"]);
+        let (tokens, _) = passthrough.tokenize("x ?é$ y", 0, 1);
+        assert_eq!((tokens[1].span.start, tokens[1].span.end), (2, 6));
+    }
+
+    #[test]
+    fn verbatim_escapes_tex() {
+        let tex = |text| TokenKind::Verbatim(text).to_tex();
+        assert_eq!(tex("# $ % & _ { }"), r"\# \$ \% \& \_ \{ \}");
+        assert_eq!(tex("~^\\"), r"\sim{}\hat{}\backslash{}");
+        assert_eq!(tex("?é!"), "?é!");
+    }
+
+    /// The regex lexer the trie replaced, kept to check that the trie lexes documents the same way.
+    ///
+    /// One combined regex finds operators and brackets, longest first, then words and numbers.
+    /// Operators made of letters only match as whole words.
+    fn regex_lex(line: &str) -> Vec<String> {
+        static RX_TOKENIZE: std::sync::LazyLock<regex::Regex> = std::sync::LazyLock::new(|| {
+            let operators = OperatorToken::sources().iter().map(|token| {
+                let [front_b, back_b] = [token.chars().next(), token.chars().next_back()]
+                    .map(|c| if c.unwrap().is_alphabetic() { r"\b" } else { "" });
+                format!("{front_b}{}{back_b}", regex::escape(token))
+            });
+            let brackets = GroupCtrlToken::sources().iter().map(|token| regex::escape(token));
+            let mut items: Vec<String> = operators.chain(brackets).collect();
+            items.sort_by_key(|item| std::cmp::Reverse(item.len()));
+            items.extend([r"\b[a-zA-Z]+\b".to_string(), r"[0-9]*\.?[0-9]+".to_string()]);
+            regex::Regex::new(&items.join("|")).unwrap()
+        });
+        RX_TOKENIZE.find_iter(line)
+            .map(|token| {
+                let text = token.as_str();
+                let kind = if let Some(op_token) = OperatorToken::try_from(text) {
+                    TokenKind::Operator(op_token)
+                } else if let Some(gc_token) = GroupCtrlToken::try_from(text) {
+                    TokenKind::GroupCtrl(gc_token)
+                } else if text.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+                    TokenKind::Number(text)
+                } else if let Some(bo_token) = BigOpToken::try_from(text) {
+                    TokenKind::BigOp(bo_token)
+                } else {
+                    TokenKind::Word(WordToken::from(text))
+                };
+                format!("{kind:?}")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn trie_matches_regex() {
+        let document = include_str!("../../../data.math");
+        let rx_directive = regex::Regex::new(r"^(@|#|(global\s+)?(let|const|fn)\s|undef\s|notation\s)").unwrap();
+        let lexer = Lexer::new();
+        for line in document.lines().map(|line| line.split('%').next().unwrap().trim()) {
+            if line.is_empty() || rx_directive.is_match(line) {
+                continue;
+            }
+            let (tokens, errors) = lexer.tokenize(line, 0, 1);
+            assert!(errors.is_empty(), "{line:?} should lex without errors");
+            let tokens: Vec<String> = tokens.iter().map(|token| format!("{:?}", token.kind)).collect();
+            assert_eq!(tokens, regex_lex(line), "{line:?}");
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use crate::{to_tex::ToTex, parser::syntax_tree::SyntaxNode, processor::DefKind};
+use super::operator::{OperatorToken, OpType};
+
+/// Where an operator defined with `notation` takes its arguments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Fixity {
+    /// Before its argument, like `-a`.
+    Prefix,
+    /// Between its arguments, like `a + b`.
+    Infix,
+    /// After its argument, like `n!`.
+    Postfix,
+}
+
+impl Fixity {
+    /// The number of arguments taken on the left and on the right.
+    pub fn nary(&self) -> (usize, usize) {
+        match self {
+            Fixity::Prefix => (0, 1),
+            Fixity::Infix => (1, 1),
+            Fixity::Postfix => (1, 0),
+        }
+    }
+}
+
+/// An operator defined by the document, like `notation "<~>" as \leftrightsquigarrow assertion precedence of =`.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct CustomOperator {
+    /// How the operator is written in the source document.
+    pub source: String,
+    /// The TeX command for the operator.
+    pub tex: String,
+    /// Whether the operator is an assertion or operation.
+    pub kind: OpType,
+    /// Where the operator takes its arguments.
+    pub fixity: Fixity,
+    /// The builtin operator whose precedence and associativity this operator shares.
+    pub like: OperatorToken,
+}
+
+impl CustomOperator {
+    /// Format the operator with its arguments as TeX.
+    pub fn format(&self, lhs: Vec<SyntaxNode<'_>>, rhs: Vec<SyntaxNode<'_>>) -> String {
+        let args = |nodes: Vec<SyntaxNode<'_>>| nodes.into_iter()
+            .map(|node| format!("{{{}}}", node.to_tex()))
+            .collect::<String>();
+        format!("{}{}{{{}}}{}", args(lhs), self.kind.to_tex(), self.tex, args(rhs))
+    }
+}
+
+/// A pair of brackets defined by the document, like `notation "[|" "|]" as \llbracket \rrbracket`.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct CustomBrackets {
+    /// How the opening bracket is written in the source document.
+    pub open: String,
+    /// How the closing bracket is written in the source document.
+    pub close: String,
+    /// The TeX command for the opening bracket.
+    pub open_tex: String,
+    /// The TeX command for the closing bracket.
+    pub close_tex: String,
+}
+
+/// A word defined by the document, like `notation "grad" as \nabla function`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CustomWord {
+    /// How the word is written in the source document.
+    pub source: String,
+    /// The TeX command for the word.
+    pub tex: String,
+    /// What kind of object the word refers to.
+    pub kind: DefKind,
+}
+
+/// Every piece of notation a document has added to the language.
+///
+/// Operators and brackets are shared with the tokens made from them, and are freed along with the last of those tokens.
+#[derive(Debug, Clone, Default, Hash)]
+pub struct Notation {
+    /// Operators, in the order they were added.
+    pub operators: Vec<Arc<CustomOperator>>,
+    /// Bracket pairs, in the order they were added.
+    pub brackets: Vec<Arc<CustomBrackets>>,
+    /// Words, in the order they were added.
+    pub words: Vec<CustomWord>,
+}
+
+impl Notation {
+    /// Find the operator written as `token`.
+    pub fn operator(&self, token: &str) -> Option<&Arc<CustomOperator>> {
+        self.operators.iter().find(|op| op.source == token)
+    }
+
+    /// Find the bracket pair that `token` opens or closes, and whether it opens.
+    pub fn bracket(&self, token: &str) -> Option<(&Arc<CustomBrackets>, bool)> {
+        self.brackets.iter().find_map(|brackets|
+            if brackets.open == token {
+                Some((brackets, true))
+            } else if brackets.close == token {
+                Some((brackets, false))
+            } else {
+                None
+            }
+        )
+    }
+
+    /// Find the word written as `token`. Later notation for the same word replaces earlier notation.
+    pub fn word(&self, token: &str) -> Option<&CustomWord> {
+        self.words.iter().rev().find(|word| word.source == token)
+    }
+
+    /// Whether `token` already means something, either builtin or added by the document.
+    pub fn is_taken(&self, token: &str) -> bool {
+        OperatorToken::try_from(token).is_some()
+            || super::GroupCtrlToken::try_from(token).is_some()
+            || self.operator(token).is_some()
+            || self.bracket(token).is_some()
+    }
+}
@@ -1,4 +1,7 @@
+use std::sync::Arc;
+
 use crate::{to_tex::ToTex, parser::syntax_tree::SyntaxNode};
+use super::notation::CustomOperator;
 
 macro_rules! as_one {
     ($item:tt) => {
@@ -32,13 +35,23 @@ macro_rules! op_fmt_arg {
     };
 }
 
+macro_rules! assoc_or_left {
+    () => {
+        Assoc::Left
+    };
+
+    ($assoc:ident) => {
+        Assoc::$assoc
+    };
+}
+
 macro_rules! operator_tokens {
     {
         $(#[$meta:meta])*
         $vis:vis enum $name:ident { $(
             { $(
                 $(#[$variant_meta:meta])*
-                @$kind:ident
+                @$kind:ident $(($assoc:ident))?
                 $($token:literal)|+ => $variant:ident => $tex:literal,
                 $(
                     ([$($lhs_fmt:tt),*] $op_fmt:ident $(<$kind_fmt:ident>)? [$($rhs_fmt:tt),*]) => $nary_fmt:literal,
@@ -52,6 +65,8 @@ macro_rules! operator_tokens {
                 $(#[$variant_meta])*
                 $variant,
             )*)*
+            /// An operator added by the document with the `notation` directive
+            Custom(Arc<CustomOperator>),
         }
 
         impl $name {
@@ -69,6 +84,16 @@ macro_rules! operator_tokens {
                 )*
             ];
 
+            /// The preferred string for writing this operator in the source document.
+            pub fn source_str(&self) -> &str {
+                match self {
+                    $($(
+                        Self::$variant => [$( $token ),*][0],
+                    )*)*
+                    Self::Custom(op) => &op.source,
+                }
+            }
+
             /// Try to construct an operator token. Returns `None` if the token isn't an operator.
             pub fn try_from(token: &str) -> Option<Self> {
                 match token {
@@ -92,6 +117,7 @@ macro_rules! operator_tokens {
                             ),
                         )*],
                     )*)*
+                    Self::Custom(op) => vec![op.fixity.nary()],
                 }
             }
 
@@ -103,7 +129,7 @@ macro_rules! operator_tokens {
                 match (self, &lhs[..], &rhs[..]) {
                     $($($(
                         (Self::$variant, [$(op_fmt_pat!($lhs_fmt)),*], [$(op_fmt_pat!($rhs_fmt)),*]) => {
-                            let $op_fmt: String = self.to_tex();
+                            let $op_fmt: String = self.clone().to_tex();
                             $(
                                 let $kind_fmt: String = self.kind().to_tex();
                             )?
@@ -118,26 +144,39 @@ macro_rules! operator_tokens {
                             format!($nary_fmt)
                         },
                     )*)*)*
+                    (Self::Custom(op), _, _) => op.format(lhs, rhs),
                     _ => unimplemented!("No operator at the time of writing supports the arguments `{lhs:?} op {rhs:?}`. Has {self:?} been correctly implemented?"),
                 }
             }
 
+            /// How the operator groups with operators of the same precedence.
+            pub fn assoc(&self) -> Assoc {
+                match self {
+                    $($(
+                        Self::$variant => assoc_or_left!($($assoc)?),
+                    )*)*
+                    Self::Custom(op) => op.like.assoc(),
+                }
+            }
+
             /// Whether the operator is an assertion or operation.
             pub fn kind(&self) -> OpType {
                 match self {
                     $($(
                         Self::$variant => OpType::$kind,
                     )*)*
+                    Self::Custom(op) => op.kind,
                 }
             }
         }
 
         impl ToTex for $name {
             fn to_tex(self) -> String {
-                match self {
+                match &self {
                     $($(
                         Self::$variant => $tex,
                     )*)*
+                    Self::Custom(op) => &op.tex,
                 }.to_string()
             }
         }
@@ -145,7 +184,7 @@ macro_rules! operator_tokens {
 }
 
 /// Whether the [`OperatorToken`] is an operator or assertion
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum OpType {
     /// Represents an operation (\mathbin)
     Operation,
@@ -153,6 +192,20 @@ pub enum OpType {
     Assertion,
 }
 
+/// How an [`OperatorToken`] groups with operators of the same precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Assoc {
+    /// `a - b - c` is `(a - b) - c`
+    Left,
+    /// `a ^ b ^ c` is `a ^ (b ^ c)`
+    Right,
+    /// `a < b < c` means `a < b` and `b < c`.
+    /// Parsed like [`Assoc::Left`], as `(a < b) < c`; the type checker then compares `b` and `c` for the outer operator.
+    Chain,
+    /// `a choose b choose c` is an error, since neither grouping means anything
+    None,
+}
+
 impl ToTex for OpType {
     fn to_tex(self) -> String {
         match self {
@@ -163,23 +216,16 @@ impl ToTex for OpType {
 }
 
 impl OperatorToken {
-    /// Lists every source token with regex escaping
-    pub fn regex_items() -> Vec<String> {
-        let mut tokens: Vec<_> = Self::TOKENS
-            .iter()
-            .map(|token| {
-                let [front_b, back_b] = [
-                    token.chars().next(),
-                    token.chars().next_back()
-                ].map(|x| if char::is_alphabetic(x.unwrap()) { r"\b" } else { "" });
-                format!("{front_b}{}{back_b}", regex::escape(token))
-            }).collect();
-        tokens.sort_by(|a, b| b.len().cmp(&a.len()));
-        tokens
+    /// Every string that is a builtin operator in the source document.
+    pub fn sources() -> &'static [&'static str] {
+        Self::TOKENS
     }
 
     /// The order in which the operator should be evaluated
     pub fn precedence(&self) -> usize {
+        if let Self::Custom(op) = self {
+            return op.like.precedence();
+        }
         Self::PRECEDENCES.iter()
             .enumerate()
             .find_map(|(n, items)| items.contains(self).then_some(n))
@@ -191,16 +237,28 @@ impl OperatorToken {
     pub fn bind_power(&self) -> usize {
         Self::PRECEDENCES.len() - self.precedence()
     }
+
+    /// The binding power the operator has over the arguments to its left and to its right, respectively.
+    ///
+    /// Offsetting one side from the other is what makes operators of the same precedence lean one way or the other.
+    /// Prefix forms only use the right power, and postfix forms only use the left power.
+    pub fn infix_power(&self) -> (usize, usize) {
+        let power = self.bind_power() * 2;
+        match self.assoc() {
+            Assoc::Left | Assoc::Chain | Assoc::None => (power, power + 1),
+            Assoc::Right => (power + 1, power),
+        }
+    }
 }
 
 operator_tokens!{
     /// A token that specifically represents an operator.
     /// Operators look around to find their arguments.
-    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
     pub enum OperatorToken {
         {
             /// Distinguishment or collection indexing
-            @Operation "_" => Subscript => "_",
+            @Operation(Right) "_" => Subscript => "_",
             ([l0] op [(r0)]) => r"{{{l0}}}{op}{{{r0}}}",
         },
         {
@@ -218,7 +276,7 @@ operator_tokens!{
         },
         {
             /// Exponent
-            @Operation "^" => Superscript => "^",
+            @Operation(Right) "^" => Superscript => "^",
             ([base] op<kind> [(power)]) => r"{{{base}}}{kind}{{{op}}}{{{power}}}",
         },
         {
@@ -253,41 +311,53 @@ operator_tokens!{
         },
         {
             /// Binomial coefficient
-            @Operation "choose" => Choose => r"\binom",
+            @Operation(None) "choose" => Choose => r"\binom",
             ([l0] op<kind> [r0]) => r"{{{l0}}}{kind}{{{op}}}{{{r0}}}",
         },
         {
             /// Limit approach
-            @Operation "->" => To => r"\to",
+            @Operation(Right) "->" => To => r"\to",
             ([l0] op<kind> [r0]) => r"{{{l0}}}{kind}{{{op}}}{{{r0}}}",
 
             /// Reserved for future assignment
-            @Operation "<-" => Gets => r"\gets",
+            @Operation(Right) "<-" => Gets => r"\gets",
             ([l0] op<kind> [r0]) => r"{{{l0}}}{kind}{{{op}}}{{{r0}}}",
         },
+        {
+            /// Range including the upper bound, like `0..n` or `0..=n`
+            @Operation(None) ".." | "..=" => Range => r"\ldots",
+            ([l0] op [r0]) => r"{{{l0}}}, {op}, {{{r0}}}",
+            ([l0] op []) => r"{{{l0}}}, {op}",
+            ([] op [r0]) => r"{op}, {{{r0}}}",
+
+            /// Range excluding the upper bound, like `0..<n`
+            @Operation(None) "..<" => RangeExclusive => r"\ldots",
+            ([l0] op [r0]) => r"{{{l0}}}, {op}, {{{r0}}}\op{{-}}{{\lit{{1}}}}",
+            ([] op [r0]) => r"{op}, {{{r0}}}\op{{-}}{{\lit{{1}}}}",
+        },
         {
             /// Greater than
-            @Assertion ">" => Gt => ">",
+            @Assertion(Chain) ">" => Gt => ">",
             ([l0] op<kind> [r0]) => r"{{{l0}}}{kind}{{{op}}}{{{r0}}}",
 
             /// Greater than or equal to
-            @Assertion ">=" => Ge => r"\ge",
+            @Assertion(Chain) ">=" => Ge => r"\ge",
             ([l0] op<kind> [r0]) => r"{{{l0}}}{kind}{{{op}}}{{{r0}}}",
 
             /// Less than
-            @Assertion "<" => Lt => r"<",
+            @Assertion(Chain) "<" => Lt => r"<",
             ([l0] op<kind> [r0]) => r"{{{l0}}}{kind}{{{op}}}{{{r0}}}",
 
             /// Less than or equal to
-            @Assertion "<=" => Le => r"\le",
+            @Assertion(Chain) "<=" => Le => r"\le",
             ([l0] op<kind> [r0]) => r"{{{l0}}}{kind}{{{op}}}{{{r0}}}",
 
             /// Element of
-            @Assertion "in" => In => r"\in",
+            @Assertion(Chain) "in" => In => r"\in",
             ([l0] op<kind> [r0]) => r"{{{l0}}}{kind}{{{op}}}{{{r0}}}",
 
             /// Similar to
-            @Assertion "~" => Sim => r"\sim",
+            @Assertion(Chain) "~" => Sim => r"\sim",
             ([l0] op<kind> [r0]) => r"{{{l0}}}{kind}{{{op}}}{{{r0}}}",
         },
         {
@@ -313,19 +383,19 @@ operator_tokens!{
         },
         {
             /// Equality
-            @Assertion "==" | "=" => Eq => "=",
+            @Assertion(Chain) "==" | "=" => Eq => "=",
             ([l0] op<kind> [r0]) => r"{{{l0}}}{kind}{{{op}}}{{{r0}}}",
 
             /// Inequality
-            @Assertion "!=" | "=/=" => Ne => r"\ne",
+            @Assertion(Chain) "!=" | "=/=" => Ne => r"\ne",
             ([l0] op<kind> [r0]) => r"{{{l0}}}{kind}{{{op}}}{{{r0}}}",
 
             /// Equivalence
-            @Assertion "===" => Equiv  => r"\equiv",
+            @Assertion(Chain) "===" => Equiv  => r"\equiv",
             ([l0] op<kind> [r0]) => r"{{{l0}}}{kind}{{{op}}}{{{r0}}}",
 
             /// Inequivalence
-            @Assertion "!==" => NEquiv => r"\nequiv",
+            @Assertion(Chain) "!==" => NEquiv => r"\nequiv",
             ([l0] op<kind> [r0]) => r"{{{l0}}}{kind}{{{op}}}{{{r0}}}",
         },
         {
@@ -385,7 +455,7 @@ operator_tokens!{
         },
         {
             /// Mapping
-            @Assertion "|=>" | "|->" => MapsTo => r"\mapsto",
+            @Assertion(Right) "|=>" | "|->" => MapsTo => r"\mapsto",
             ([l0] op<kind> [r0]) => r"{{{l0}}}{kind}{{{op}}}{{{r0}}}",
 
             /// Mapping
@@ -394,7 +464,7 @@ operator_tokens!{
         },
         {
             /// If A then B
-            @Assertion "==>" | "=>" => Implies => r"\implies",
+            @Assertion(Right) "==>" | "=>" => Implies => r"\implies",
             ([l0] op<kind> [r0]) => r"{{{l0}}}{kind}{{{op}}}{{{r0}}}",
 
             /// If B then A
@@ -402,7 +472,7 @@ operator_tokens!{
             ([l0] op<kind> [r0]) => r"{{{l0}}}{kind}{{{op}}}{{{r0}}}",
 
             /// A, B only if A AND B
-            @Assertion "<=>" => Iff => r"\iff",
+            @Assertion(Chain) "<=>" => Iff => r"\iff",
             ([l0] op<kind> [r0]) => r"{{{l0}}}{kind}{{{op}}}{{{r0}}}",
         },
         {
@@ -419,5 +489,10 @@ operator_tokens!{
             @Assertion "where" => Where => r"\where",
             ([l0] op<kind> [r0]) => r"{{{l0}}}{kind}{{{op}}}{{{r0}}}",
         },
+        {
+            /// Separates items in a list, like the bounds of a summation
+            @Operation "," => Comma => ",",
+            ([l0] op [r0]) => r"{{{l0}}}{op} {{{r0}}}",
+        },
     }
 }
@@ -0,0 +1,61 @@
+/// Identifies a file in a [`SourceMap`](crate::source_map::SourceMap). The file given to the program is `0`.
+pub type FileId = usize;
+
+/// A region of the source document.
+///
+/// `start` and `end` are byte offsets into the line, `end` being exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    /// The file the region is in.
+    pub file: FileId,
+    /// The line the region is on, starting from 1.
+    pub line: usize,
+    /// Byte offset of the first character in the region.
+    pub start: usize,
+    /// Byte offset one past the last character in the region.
+    pub end: usize,
+}
+
+impl Span {
+    /// Construct a span from its file, line, and byte range.
+    pub fn new(file: FileId, line: usize, start: usize, end: usize) -> Self {
+        Self { file, line, start, end }
+    }
+
+    /// The smallest span containing both `self` and `other`.
+    pub fn to(self, other: Self) -> Self {
+        Self {
+            file: self.file,
+            line: self.line.min(other.line),
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+
+    /// The number of bytes covered by the span.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Whether the span covers no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// The text the span covers within `line`, or nothing if the span isn't on a character boundary of `line`.
+    pub fn text<'a>(&self, line: &'a str) -> &'a str {
+        line.get(self.start..self.end).unwrap_or_default()
+    }
+
+    /// The 1-based column of the start of the span within `line`, counted in characters.
+    pub fn column(&self, line: &str) -> usize {
+        line.get(..self.start)
+            .map_or(self.start, |prefix| prefix.chars().count()) + 1
+    }
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}..{}", self.line, self.start, self.end)
+    }
+}
@@ -3,10 +3,12 @@ use crate::to_tex::ToTex;
 use super::operator::*;
 use super::group_ctrl::*;
 use super::word::*;
+use super::big_op::*;
+use super::span::Span;
 
-/// A single token from the source document.
-#[derive(Clone, Copy)]
-pub enum Token<'doc> {
+/// What a [`Token`] represents.
+#[derive(Clone)]
+pub enum TokenKind<'doc> {
     /// The name of a variable, constant, or function
     Word(WordToken<'doc>),
 
@@ -16,11 +18,17 @@ pub enum Token<'doc> {
     /// A mathematical operator which may look at nodes to its left or right
     Operator(OperatorToken),
 
+    /// An operator like a summation or root, which takes an optional index and an operand
+    BigOp(BigOpToken),
+
     /// A delimiter indicating the start or end of a subexpression
     GroupCtrl(GroupCtrlToken),
+
+    /// Characters that aren't part of the language, copied into the output with TeX's special characters escaped
+    Verbatim(&'doc str),
 }
 
-impl<'doc> std::fmt::Debug for Token<'doc> {
+impl<'doc> std::fmt::Debug for TokenKind<'doc> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         // force regular debug even if using "pretty" debug
         match self {
@@ -30,29 +38,82 @@ impl<'doc> std::fmt::Debug for Token<'doc> {
                 => write!(f, "Number({arg0:?})"),
             Self::Operator(arg0)
                 => write!(f, "Operator({arg0:?})"),
+            Self::BigOp(arg0)
+                => write!(f, "BigOp({arg0:?})"),
             Self::GroupCtrl(arg0)
                 => write!(f, "GroupCtrl({arg0:?})"),
+            Self::Verbatim(arg0)
+                => write!(f, "Verbatim({arg0:?})"),
         }
     }
 }
 
-impl<'doc> ToTex for Token<'doc> {
+impl<'doc> ToTex for TokenKind<'doc> {
     fn to_tex(self) -> String {
         match self {
             Self::Number(token)
                 => format!(r"\lit{{{token}}}"),
 
-            Self::Word(WordToken::Direct(dw_token))
-                => dw_token.to_tex(),
+            Self::Verbatim(text)
+                => escape_tex(text),
 
-            Self::Word(WordToken::Builtin(bw_token))
-                => bw_token.to_tex(),
+            Self::Word(word_token)
+                => word_token.to_tex(),
 
             Self::Operator(op_token)
                 => op_token.to_tex(),
 
+            Self::BigOp(bo_token)
+                => bo_token.to_tex(),
+
             Self::GroupCtrl(gc_token)
                 => gc_token.to_tex(),
         }
     }
 }
+
+/// Escape the characters that TeX gives a special meaning, so that `text` is typeset as it is written.
+fn escape_tex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '#' | '$' | '%' | '&' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            },
+            '~' => escaped.push_str(r"\sim{}"),
+            '^' => escaped.push_str(r"\hat{}"),
+            '\\' => escaped.push_str(r"\backslash{}"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A single token from the source document.
+#[derive(Clone)]
+pub struct Token<'doc> {
+    /// What the token represents.
+    pub kind: TokenKind<'doc>,
+    /// Where the token was found in the source document.
+    pub span: Span,
+}
+
+impl<'doc> Token<'doc> {
+    /// Construct a token from its kind and location.
+    pub fn new(kind: TokenKind<'doc>, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+impl<'doc> std::fmt::Debug for Token<'doc> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} @ {}", self.kind, self.span)
+    }
+}
+
+impl<'doc> ToTex for Token<'doc> {
+    fn to_tex(self) -> String {
+        self.kind.to_tex()
+    }
+}
@@ -0,0 +1,123 @@
+/// A prefix tree of symbols, for finding the longest symbol at the start of a string in one pass.
+#[derive(Debug, Clone)]
+pub struct Trie<T> {
+    nodes: Vec<TrieNode<T>>,
+}
+
+#[derive(Debug, Clone)]
+struct TrieNode<T> {
+    /// The character leading to each child, and the child's index in [`Trie::nodes`].
+    children: Vec<(char, usize)>,
+    /// The value of the symbol ending at this node, if one does.
+    value: Option<T>,
+}
+
+impl<T> TrieNode<T> {
+    fn new() -> Self {
+        Self { children: Vec::new(), value: None }
+    }
+
+    fn child(&self, c: char) -> Option<usize> {
+        self.children.iter().find_map(|&(child_c, index)| (child_c == c).then_some(index))
+    }
+}
+
+impl<T> Default for Trie<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Trie<T> {
+    /// Constructs a trie with no symbols.
+    pub fn new() -> Self {
+        Self { nodes: vec![TrieNode::new()] }
+    }
+
+    /// Add a symbol. Adding a symbol that is already in the trie replaces its value.
+    pub fn insert(&mut self, symbol: &str, value: T) {
+        let mut node = 0;
+        for c in symbol.chars() {
+            node = match self.nodes[node].child(c) {
+                Some(child) => child,
+                None => {
+                    self.nodes.push(TrieNode::new());
+                    let child = self.nodes.len() - 1;
+                    self.nodes[node].children.push((c, child));
+                    child
+                },
+            };
+        }
+        self.nodes[node].value = Some(value);
+    }
+
+    /// Find the longest symbol that `text` starts with and that `accept` allows, given its length in bytes and its value.
+    ///
+    /// Returns the length of the symbol in bytes along with its value.
+    pub fn longest_match(&self, text: &str, accept: impl Fn(usize, &T) -> bool) -> Option<(usize, &T)> {
+        let mut node = 0;
+        let mut longest = None;
+        for (i, c) in text.char_indices() {
+            match self.nodes[node].child(c) {
+                Some(child) => node = child,
+                None => break,
+            }
+            if let Some(value) = &self.nodes[node].value {
+                let len = i + c.len_utf8();
+                if accept(len, value) {
+                    longest = Some((len, value));
+                }
+            }
+        }
+        longest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A trie whose values are the symbols themselves.
+    fn trie(symbols: &[&'static str]) -> Trie<&'static str> {
+        let mut trie = Trie::new();
+        for symbol in symbols {
+            trie.insert(symbol, *symbol);
+        }
+        trie
+    }
+
+    #[test]
+    fn longest_match() {
+        let trie = trie(&["..", "..=", "..<", "<", "<="]);
+        assert_eq!(trie.longest_match("..n", |_, _| true), Some((2, &"..")));
+        assert_eq!(trie.longest_match("..=n", |_, _| true), Some((3, &"..=")));
+        assert_eq!(trie.longest_match("..<n", |_, _| true), Some((3, &"..<")));
+        assert_eq!(trie.longest_match("..>n", |_, _| true), Some((2, &"..")));
+        assert_eq!(trie.longest_match(".n", |_, _| true), None);
+        assert_eq!(trie.longest_match("", |_, _| true), None);
+    }
+
+    #[test]
+    fn rejected_matches_fall_back_to_shorter() {
+        let trie = trie(&["in", "int"]);
+        assert_eq!(trie.longest_match("int", |_, _| true), Some((3, &"int")));
+        assert_eq!(trie.longest_match("int", |len, _| len < 3), Some((2, &"in")));
+        assert_eq!(trie.longest_match("int", |_, _| false), None);
+    }
+
+    #[test]
+    fn later_inserts_replace_values() {
+        let mut trie = trie(&["|", "||"]);
+        trie.insert("|", "bar");
+        assert_eq!(trie.longest_match("|x", |_, _| true), Some((1, &"bar")));
+        assert_eq!(trie.longest_match("||", |_, _| true), Some((2, &"||")));
+    }
+
+    #[test]
+    fn lengths_are_in_bytes() {
+        let trie = trie(&["→", "→→"]);
+        assert_eq!(trie.longest_match("→x", |_, _| true), Some((3, &"→")));
+        assert_eq!(trie.longest_match("→→", |len, _| len <= 3), Some((3, &"→")));
+        assert_eq!(trie.longest_match("→→", |_, _| true), Some((6, &"→→")));
+    }
+}
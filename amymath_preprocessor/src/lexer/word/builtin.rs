@@ -22,6 +22,17 @@ macro_rules! builtin_word_tokens {
         }
 
         impl $name {
+            const NAMES: &'static [&'static str] = &[
+                $($($(
+                    $src_token,
+                )*)*)*
+            ];
+
+            /// Every word that is built in.
+            pub fn names() -> &'static [&'static str] {
+                Self::NAMES
+            }
+
             /// Try to construct a builtin word token. If `None`, the word is not built in.
             pub fn try_from(token: &str) -> Option<Self> {
                 match token {
@@ -78,20 +89,10 @@ builtin_word_tokens!{
             "psi" => Psi => r"\psi",
         },
         Function {
-            // Todo: Some of these operators, NOT functions.
-            // They are currently erroring in LaTeX because they aren't getting their mandatory arguments.
-
-            /// Square (or n) root
-            /// TODO: This is an OPERATOR not a function.
-            "sqrt" => Sqrt => r"\sqrt{}",
             /// Logarithm
             "log" => Log => r"\log",
             /// Natural (base-e) logarithm
             "ln" => Ln => r"\ln",
-            /// Summation
-            "sum" => Sum => r"\sum",
-            /// Production
-            "prod" => Prod => r"\prod",
             /// Zeta function
             "Zeta" => ZZeta => r"\Zeta",
 
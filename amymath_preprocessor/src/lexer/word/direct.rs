@@ -1,5 +1,12 @@
 use crate::{processor::DefKind, to_tex::ToTex};
 
+/// Split a name into its base and its subscript, without the `_`: `x1` into `x` and `1`, and `v_max` into `v` and `max`.
+pub fn split_subscript(name: &str) -> (&str, &str) {
+    let base_len = name.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(name.len());
+    let (base, subscript) = name.split_at(base_len);
+    (base, subscript.strip_prefix('_').unwrap_or(subscript))
+}
+
 /// A word token whose TeX is identical to the word.
 #[derive(Debug, Clone, Copy)]
 pub struct DirectWordToken<'doc> {
@@ -7,21 +14,41 @@ pub struct DirectWordToken<'doc> {
     pub name: &'doc str,
     /// A slot for identifying what kind of word this token is.
     pub kind: Option<DefKind>,
+    /// The TeX of the word's base, if it isn't written as itself: a builtin like `phi` in `phi1`, or a word added with `notation`.
+    pub command: Option<&'doc str>,
 }
 
 impl<'doc> DirectWordToken<'doc> {
     /// Construct a direct word token from the word it represents.
     pub fn new(name: &'doc str) -> Self {
-        Self { name, kind: None }
+        Self { name, kind: None, command: None }
+    }
+}
+
+impl<'doc> DirectWordToken<'doc> {
+    /// The TeX of the word alone, without the DefKind command.
+    ///
+    /// A subscript at the end of the name is written as one: `x1` becomes `x_{1}`, `theta0` becomes `\theta_{0}`, and `v_max` becomes `v_{\text{max}}`.
+    pub fn command(&self) -> String {
+        let (base, subscript) = split_subscript(self.name);
+        let base = self.command.unwrap_or(base);
+        if subscript.is_empty() {
+            base.to_string()
+        } else if subscript.starts_with(|c: char| c.is_ascii_digit()) {
+            format!("{base}_{{{subscript}}}")
+        } else {
+            format!(r"{base}_{{\text{{{subscript}}}}}")
+        }
     }
 }
 
 impl<'doc> ToTex for DirectWordToken<'doc> {
     fn to_tex(self) -> String {
+        let command = self.command();
         if let Some(kind) = self.kind {
-            format!("{}{{{}}}", kind.to_tex(), self.name)
+            format!("{}{{{command}}}", kind.to_tex())
         } else {
-            format!("{{{}}}", self.name)
+            format!("{{{command}}}")
         }
     }
 }
@@ -6,6 +6,8 @@ pub mod direct;
 use builtin::*;
 use direct::*;
 
+use crate::{processor::DefKind, to_tex::ToTex};
+
 /// A token representing a variable, constant, or function.
 #[derive(Debug, Clone, Copy)]
 pub enum WordToken<'doc> {
@@ -18,10 +20,52 @@ pub enum WordToken<'doc> {
 
 impl<'doc> From<&'doc str> for WordToken<'doc> {
     fn from(value: &'doc str) -> Self {
-        if let Some(bw_token) = BuiltinWordToken::try_from(&value) {
-            Self::Builtin(bw_token)
-        } else {
-            Self::Direct(DirectWordToken::new(value))
+        let (base, subscript) = split_subscript(value);
+        match BuiltinWordToken::try_from(base) {
+            Some(bw_token) if subscript.is_empty() => Self::Builtin(bw_token),
+            // A builtin with a subscript, like `theta0`, is the same kind of object as the builtin
+            Some(bw_token) => Self::Direct(DirectWordToken {
+                name: value,
+                kind: Some(bw_token.kind()),
+                command: Some(bw_token.command()),
+            }),
+            None => Self::Direct(DirectWordToken::new(value)),
+        }
+    }
+}
+
+impl<'doc> WordToken<'doc> {
+    /// The word as the user has defined it. Builtin words keep their TeX, but take on the user's [`DefKind`].
+    pub fn defined(name: &'doc str, kind: DefKind) -> Self {
+        Self::Direct(DirectWordToken {
+            name,
+            kind: Some(kind),
+            command: BuiltinWordToken::try_from(split_subscript(name).0).map(|bw_token| bw_token.command()),
+        })
+    }
+
+    /// What kind of definition the word refers to, if known.
+    pub fn kind(&self) -> Option<DefKind> {
+        match self {
+            Self::Direct(dw_token) => dw_token.kind,
+            Self::Builtin(bw_token) => Some(bw_token.kind()),
+        }
+    }
+
+    /// The word as TeX, without the DefKind command.
+    pub fn command(&self) -> String {
+        match self {
+            Self::Direct(dw_token) => dw_token.command(),
+            Self::Builtin(bw_token) => bw_token.command().to_string(),
+        }
+    }
+}
+
+impl<'doc> ToTex for WordToken<'doc> {
+    fn to_tex(self) -> String {
+        match self {
+            Self::Direct(dw_token) => dw_token.to_tex(),
+            Self::Builtin(bw_token) => bw_token.to_tex(),
         }
     }
 }
@@ -0,0 +1,45 @@
+//! AmyMath: a language for writing math in plain text, and a preprocessor for converting it into LaTeX.
+//!
+//! Most tools only need a [`Processor`]:
+//!
+//! ```no_run
+//! use amymath::{Processor, SourceMap, ToDiagnostic};
+//!
+//! let path = std::path::PathBuf::from("notes.math");
+//! let mut sources = SourceMap::new(path.clone(), std::fs::read_to_string(&path).unwrap());
+//! match Processor::new().emit_declarations(true).process(&mut sources) {
+//!     Ok((tex, _warnings)) => println!("{tex}"),
+//!     Err(report) => for error in &report.errors {
+//!         eprintln!("{}", error.to_diagnostic().render(&sources));
+//!     },
+//! }
+//! ```
+//!
+//! The [`Lexer`] and [`parse`] are available on their own for tools that work with individual lines of math.
+//! Progress is reported to stderr at the level given to [`set_verbosity`].
+
+#![warn(missing_docs)]
+
+/// A stack collection.
+pub(crate) mod stack;
+/// TeX conversion trait.
+pub(crate) mod to_tex;
+/// Tokenization module. Handles breakup.
+pub mod lexer;
+/// Parsing module. Handles lookaround and clumping.
+pub mod parser;
+/// Main preprocessor module. Applies lexer and parser, then converts to TeX.
+pub mod processor;
+/// Error reporting with excerpts of the source document.
+pub mod diagnostic;
+/// The files that make up a document.
+pub mod source_map;
+/// Reporting progress and intermediate results to stderr.
+pub(crate) mod log;
+
+pub use lexer::{Lexer, LexerError, Span, Token, UnknownChars};
+pub use parser::{parse, error::ParseError, syntax_tree::SyntaxTree};
+pub use processor::{Processor, ProcessOptions, DEFAULT_TEMPLATE, cache::LineCache, error::{PreprocError, Report}, lint::LintLevel};
+pub use diagnostic::{Diagnostic, ToDiagnostic};
+pub use source_map::SourceMap;
+pub use log::{Verbosity, VERBOSITY_VAR, set_verbosity, verbosity};
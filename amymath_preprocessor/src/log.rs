@@ -0,0 +1,79 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// The name of the environment variable that sets the verbosity when no flag does.
+pub const VERBOSITY_VAR: &str = "AMYMATH_LOG";
+
+/// How much the preprocessor reports about what it is doing. Each level includes everything below it.
+///
+/// Everything is written to stderr, so that stdout is free to carry generated output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Verbosity {
+    /// Errors only.
+    Quiet,
+    /// Errors and warnings.
+    #[default]
+    Normal,
+    /// Paths, meta items, headings, definitions, and notation as they are processed.
+    Progress,
+    /// Every line of math and the tokens it was broken into.
+    Tokens,
+    /// Every syntax tree.
+    Trees,
+}
+
+impl Verbosity {
+    /// The level `steps` more verbose than this one, stopping at the most verbose.
+    pub fn increased(self, steps: usize) -> Self {
+        Self::from_level(self as usize + steps)
+    }
+
+    /// The level with the given number, counting from [`Verbosity::Quiet`] as 0.
+    fn from_level(level: usize) -> Self {
+        match level {
+            0 => Self::Quiet,
+            1 => Self::Normal,
+            2 => Self::Progress,
+            3 => Self::Tokens,
+            _ => Self::Trees,
+        }
+    }
+
+    /// Read a level by name (`quiet`, `normal`, `progress`, `tokens`, `trees`) or by number.
+    pub fn try_from(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "quiet" => Some(Self::Quiet),
+            "normal" => Some(Self::Normal),
+            "progress" => Some(Self::Progress),
+            "tokens" => Some(Self::Tokens),
+            "trees" => Some(Self::Trees),
+            number => number.parse().ok().map(Self::from_level),
+        }
+    }
+}
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+/// Set how much is reported for the rest of the program.
+pub fn set_verbosity(verbosity: Verbosity) {
+    VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+}
+
+/// The current verbosity.
+pub fn verbosity() -> Verbosity {
+    Verbosity::from_level(VERBOSITY.load(Ordering::Relaxed) as usize)
+}
+
+/// Whether messages at `level` are reported.
+pub fn enabled(level: Verbosity) -> bool {
+    verbosity() >= level
+}
+
+/// Write a message to stderr if the current [`Verbosity`] includes `level`.
+macro_rules! log {
+    ($level:expr, $($arg:tt)*) => {
+        if $crate::log::enabled($level) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+pub(crate) use log;
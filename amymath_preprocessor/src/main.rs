@@ -1,41 +1,177 @@
-//! A preprocessor for converting AmyMath into LaTeX.
+//! Command-line preprocessor for converting AmyMath into LaTeX, built on the `amymath` library.
 
 #![warn(missing_docs)]
 
-use std::{fs::File, io::Write};
+use std::{fs::File, io::Write, process::ExitCode};
+
+/// Write a message to stderr if the current [`Verbosity`] includes `level`.
+macro_rules! log {
+    ($level:expr, $($arg:tt)*) => {
+        if amymath::verbosity() >= $level {
+            eprintln!($($arg)*);
+        }
+    };
+}
 
 /// Extracts information from command-line arguments.
 pub mod program_args;
-/// A stack collection.
-pub mod stack;
-/// TeX conversion trait.
-pub mod to_tex;
-/// Tokenization module. Handles breakup.
-pub mod lexer;
-/// Parsing module. Handles lookaround and clumping.
-pub mod parser;
-/// Main preprocessor module. Applies lexer and parser, then converts to TeX.
-pub mod processor;
-
-use processor::process_document;
+/// Rebuilding the document whenever one of its files changes.
+pub mod watch;
+/// Tidying the layout of source documents.
+pub mod format;
+
+use amymath::{LineCache, Processor, Report, SourceMap, ToDiagnostic, Verbosity};
+use format::format_document;
 use program_args::*;
 
-fn main() -> std::io::Result<()> {
+fn main() -> ExitCode {
     match ProgramArgs::try_from(std::env::current_dir().unwrap(), std::env::args()) {
         Err(error) => {
             eprintln!("Argument Error: {error}");
-            Err(std::io::Error::other(error))
+            eprintln!("Run `{} --help` for usage.", env!("CARGO_PKG_NAME"));
+            ExitCode::FAILURE
         },
-        Ok(args) => {
-            match process_document(&args.source, &args.template) {
-                Err(error) => {
-                    eprintln!("Preprocessor Error: {error}");
-                    Err(std::io::Error::other(error))
-                },
-                Ok(output) => {
-                    File::create(&args.out_path)?.write_all(output.as_bytes())
+        Ok(Invocation::Help(command)) => {
+            print!("{}", cli::help(command));
+            ExitCode::SUCCESS
+        },
+        Ok(Invocation::Version) => {
+            println!("{}", cli::version());
+            ExitCode::SUCCESS
+        },
+        Ok(Invocation::Run(args)) => {
+            amymath::set_verbosity(args.verbosity);
+            match args.command {
+                Command::Build => build(&args),
+                Command::Check => check(&args),
+                Command::Fmt => write_output(&args.output, &format_document(&args.source), false),
+                Command::Watch => watch::watch(&args),
+                Command::Dump => {
+                    amymath::set_verbosity(args.verbosity.max(args.dump_stage));
+                    check(&args)
                 },
             }
         },
     }
 }
+
+/// Process the document and write the output.
+fn build(args: &ProgramArgs) -> ExitCode {
+    match &args.output {
+        Output::File(out_path) => log!(Verbosity::Progress, "output path: {}", out_path.display()),
+        Output::Stdout => log!(Verbosity::Progress, "output path: <stdout>"),
+    }
+    let mut cache = load_cache(args);
+    let output = process(args, &mut source_map(args, args.source.clone()), &args.template, cache.as_mut());
+    save_cache(args, cache.as_mut());
+    match output {
+        Some(output) => write_output(&args.output, &output, true),
+        None => ExitCode::FAILURE,
+    }
+}
+
+/// Process the document and report problems without writing anything.
+fn check(args: &ProgramArgs) -> ExitCode {
+    let mut cache = load_cache(args);
+    let output = process(args, &mut source_map(args, args.source.clone()), &args.template, cache.as_mut());
+    save_cache(args, cache.as_mut());
+    match output {
+        Some(_) => ExitCode::SUCCESS,
+        None => ExitCode::FAILURE,
+    }
+}
+
+/// Read the cache given with `--cache`, if any. A cache that can't be read is started over.
+pub fn load_cache(args: &ProgramArgs) -> Option<LineCache> {
+    let cache_path = args.cache_path.as_ref()?;
+    match LineCache::load(cache_path) {
+        Ok(cache) => Some(cache),
+        Err(error) => {
+            log!(Verbosity::Normal, "warning: ignoring cache {}: {error}", cache_path.display());
+            Some(LineCache::new())
+        },
+    }
+}
+
+/// Keep only the lines the cache was used for this run, and write it back to where it was given with `--cache`.
+pub fn save_cache(args: &ProgramArgs, cache: Option<&mut LineCache>) {
+    let Some(cache) = cache else {
+        return;
+    };
+    let (hits, misses) = cache.stats();
+    log!(Verbosity::Progress, "cache: {hits} lines reused, {misses} rendered");
+    cache.prune();
+    if let Some(cache_path) = &args.cache_path {
+        if let Err(error) = cache.save(cache_path) {
+            log!(Verbosity::Normal, "warning: could not save cache {}: {error}", cache_path.display());
+        }
+    }
+}
+
+/// The source map for the document given on the command line, with `source` as its content.
+pub fn source_map(args: &ProgramArgs, source: String) -> SourceMap {
+    match &args.source_path {
+        Some(source_path) => SourceMap::new(source_path.clone(), source),
+        None => SourceMap::from_stdin(std::env::current_dir().unwrap(), source),
+    }
+}
+
+/// Process the document in `sources` into `template`, printing its diagnostics. `None` if there were errors.
+///
+/// Lines in `cache` are reused rather than rendered again.
+pub fn process(args: &ProgramArgs, sources: &mut SourceMap, template: &str, cache: Option<&mut LineCache>) -> Option<String> {
+    log!(Verbosity::Progress, "source path: {}", sources.file(0).name);
+    let processor = Processor::new()
+        .template(template)
+        .options(args.options.clone());
+    let result = match cache {
+        Some(cache) => processor.process_cached(sources, cache),
+        None => processor.process(sources),
+    };
+    let sources = &*sources;
+    let print_warnings = |report: &Report| {
+        if amymath::verbosity() < Verbosity::Normal {
+            return;
+        }
+        for warning in &report.warnings {
+            eprintln!("{}", warning.to_diagnostic().render(sources));
+        }
+        if let Some(summary) = report.warning_summary() {
+            eprintln!("warning: {summary}");
+        }
+    };
+    match result {
+        Err(report) => {
+            print_warnings(&report);
+            for error in &report.errors {
+                eprintln!("{}", error.to_diagnostic().render(sources));
+            }
+            eprintln!("error: {}", report.summary());
+            None
+        },
+        Ok((output, report)) => {
+            print_warnings(&report);
+            Some(output)
+        },
+    }
+}
+
+/// Write `content` where it was asked to go, marking files as generated if `mark_generated`.
+pub fn write_output(output: &Output, content: &str, mark_generated: bool) -> ExitCode {
+    let written = match output {
+        Output::File(out_path) => File::create(out_path).and_then(|mut file| {
+            if mark_generated {
+                writeln!(file, "{GENERATED_MARKER}")?;
+            }
+            file.write_all(content.as_bytes())
+        }),
+        Output::Stdout => std::io::stdout().lock().write_all(content.as_bytes()),
+    };
+    match written {
+        Err(error) => {
+            eprintln!("IO Error: {error}");
+            ExitCode::FAILURE
+        },
+        Ok(()) => ExitCode::SUCCESS,
+    }
+}
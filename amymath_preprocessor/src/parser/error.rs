@@ -1,50 +1,149 @@
 use std::error::Error;
-use crate::lexer::operator::OperatorToken;
+use crate::diagnostic::{Diagnostic, ToDiagnostic};
+use crate::lexer::{operator::OperatorToken, span::Span};
 use super::{BracketKind, GroupCtrlToken};
 
 /// An error that occurs while parsing.
 #[derive(Debug)]
 pub enum ParseError {
     /// The global scope was popped.
-    TooManyCloseBrackets,
+    TooManyCloseBrackets{
+        /// The close bracket that had no group to close.
+        span: Span,
+    },
     /// The document ended with excess scopes.
-    NotEnoughCloseBrackets,
+    NotEnoughCloseBrackets{
+        /// The open bracket of the group that was never closed.
+        span: Span,
+    },
     /// An operator in the source has fewer arguments than are valid for that operator.
     OperatorMissingArguments{
         /// The number of items available to the left of the operator.
         num_lhs: usize,
         /// The operator.
         op_token: OperatorToken,
+        /// The operator as it was written.
+        op_str: String,
         /// The number of items available to the right of the operator.
         num_rhs: usize,
+        /// Where the operator is.
+        span: Span,
+    },
+    /// A big operator like `sum` has nothing after it to apply to.
+    MissingOperand{
+        /// The operator and its index.
+        span: Span,
+    },
+    /// Two operators of the same precedence were used in a row, and at least one of them is [`Assoc::None`](crate::lexer::operator::Assoc::None).
+    NonAssociative{
+        /// The first of the two operators, as it was written.
+        first: String,
+        /// Where the first operator is.
+        first_span: Span,
+        /// The second of the two operators, as it was written.
+        second: String,
+        /// Where the second operator is.
+        span: Span,
     },
     /// A group was closed with a bracket that isn't compatible with the bracket it was opened with.
     BracketMismatch{
         /// The bracket that opened the group in the source document.
         opened_with: BracketKind,
+        /// Where the group was opened.
+        open_span: Span,
         /// The bracket that closed the group in the source document.
         closed_with: BracketKind,
+        /// Where the group was closed.
+        close_span: Span,
     },
 }
 
 impl std::fmt::Display for ParseError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ParseError::TooManyCloseBrackets
+            ParseError::TooManyCloseBrackets { .. }
                 => write!(f, "More bracket/brace/parentheses groups were closed than opened"),
-            ParseError::NotEnoughCloseBrackets
+            ParseError::NotEnoughCloseBrackets { .. }
                 => write!(f, "More bracket/brace/parentheses groups were opened than closed"),
-            ParseError::OperatorMissingArguments { num_lhs, op_token, num_rhs }
-                => write!(f, "No version of `{op_token:?}` operator takes {num_lhs} left-hand arguments and {num_rhs} right-hand arguments."),
-            ParseError::BracketMismatch { opened_with, closed_with }
+            ParseError::OperatorMissingArguments { num_lhs, op_str, num_rhs, .. }
+                => write!(f, "No version of the `{op_str}` operator takes {num_lhs} left-hand arguments and {num_rhs} right-hand arguments."),
+            ParseError::MissingOperand { .. }
+                => write!(f, "Big operator has no operand"),
+            ParseError::NonAssociative { first, second, .. }
+                => write!(f, "`{first}` and `{second}` cannot be used in a row without parentheses"),
+            ParseError::BracketMismatch { opened_with, closed_with, .. }
                 => write!(f, "Mismatched bracket pair: \"{}\" is incompatible with \"{}\"",
-                    GroupCtrlToken::open(*opened_with).source_str(),
-                    GroupCtrlToken::close(*closed_with).source_str(),
+                    GroupCtrlToken::open(opened_with.clone()).source_str(),
+                    GroupCtrlToken::close(closed_with.clone()).source_str(),
                 ),
         }
     }
 }
 
+impl ParseError {
+    /// Where in the source document the error occurred.
+    pub fn span(&self) -> Span {
+        match self {
+            | ParseError::TooManyCloseBrackets { span }
+            | ParseError::NotEnoughCloseBrackets { span }
+            | ParseError::OperatorMissingArguments { span, .. }
+            | ParseError::MissingOperand { span }
+            | ParseError::NonAssociative { span, .. }
+                => *span,
+            ParseError::BracketMismatch { close_span, .. }
+                => *close_span,
+        }
+    }
+}
+
+impl ToDiagnostic for ParseError {
+    fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = Diagnostic::error(self.to_string());
+        match self {
+            ParseError::TooManyCloseBrackets { span }
+                => diagnostic
+                    .with_primary(*span, "there is no open group for this to close")
+                    .with_help("remove this bracket or open a group before it"),
+
+            ParseError::NotEnoughCloseBrackets { span }
+                => diagnostic
+                    .with_primary(*span, "this group is never closed")
+                    .with_help("groups cannot be continued onto the next line"),
+
+            ParseError::OperatorMissingArguments { op_token, op_str: src, span, .. } => {
+                let forms = op_token.nary()
+                    .into_iter()
+                    .map(|(num_lhs, num_rhs)| format!("`{}{src}{}`",
+                        "a ".repeat(num_lhs),
+                        " b".repeat(num_rhs),
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(" or ");
+                diagnostic
+                    .with_primary(*span, "this operator is missing arguments")
+                    .with_help(format!("`{src}` is written as {forms}"))
+            },
+
+            ParseError::MissingOperand { span }
+                => diagnostic
+                    .with_primary(*span, "expected an operand after this")
+                    .with_help("big operators are written before what they apply to, like `sum[i = 0, n] i^2`"),
+
+            ParseError::NonAssociative { first, first_span, second, span }
+                => diagnostic
+                    .with_primary(*span, format!("follows `{first}` at the same precedence"))
+                    .with_secondary(*first_span, "first operator")
+                    .with_help(format!("add parentheses to choose a grouping, like `(a {first} b) {second} c`")),
+
+            ParseError::BracketMismatch { opened_with, open_span, closed_with, close_span }
+                => diagnostic
+                    .with_primary(*close_span, format!("closed with \"{}\"", GroupCtrlToken::close(closed_with.clone()).source_str()))
+                    .with_secondary(*open_span, format!("opened with \"{}\"", GroupCtrlToken::open(opened_with.clone()).source_str()))
+                    .with_help(format!("close the group with \"{}\"", GroupCtrlToken::close(opened_with.clone()).source_str())),
+        }
+    }
+}
+
 impl Error for ParseError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         None
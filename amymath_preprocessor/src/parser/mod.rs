@@ -1,4 +1,7 @@
+use std::collections::VecDeque;
+
 use crate::lexer::*;
+use crate::processor::DefKind;
 use crate::stack::*;
 
 /// Syntax tree for the parser.
@@ -15,37 +18,40 @@ fn group_subexpressions<'doc>(tokens: Vec<Token<'doc>>) -> Result<SyntaxTree<'do
     stack.push(SyntaxTree::new());
     // Form groups
     for token in tokens {
-        match token {
-            Token::GroupCtrl(GroupCtrlToken { kind: _, ctrl: GroupControl::Open }) => {
+        match token.kind {
+            TokenKind::GroupCtrl(GroupCtrlToken { kind: _, ctrl: GroupControl::Open }) => {
                 let mut new_group = SyntaxTree::new();
                 new_group.push_token(token);
                 stack.push(new_group);
             },
 
-            Token::GroupCtrl(GroupCtrlToken { kind: _, ctrl: GroupControl::Close }) => {
-                let mut group = stack.pop()
-                    .ok_or(ParseError::TooManyCloseBrackets)?;
-                group.push_token(token);
-                let mut iter = group.0.into_iter();
-                if let (
-                    Some(SyntaxNode::Token(Token::GroupCtrl(GroupCtrlToken { kind: opened_with, ctrl: GroupControl::Open  }))),
-                    Some(SyntaxNode::Token(Token::GroupCtrl(GroupCtrlToken { kind: closed_with, ctrl: GroupControl::Close }))),
-                 ) = (iter.next(), iter.next_back()) {
+            TokenKind::GroupCtrl(GroupCtrlToken { kind: closed_with, ctrl: GroupControl::Close }) => {
+                let group = stack.pop()
+                    .ok_or(ParseError::TooManyCloseBrackets { span: token.span })?;
+                let mut iter = group.nodes.into_iter();
+                if let Some(SyntaxNode::Token(Token {
+                    kind: TokenKind::GroupCtrl(GroupCtrlToken { kind: opened_with, ctrl: GroupControl::Open }),
+                    span: open_span,
+                })) = iter.next() {
                     if opened_with.is_compatible(&closed_with) {
-                        stack.top_mut().unwrap()
-                            .push_group(
-                                opened_with,
-                                iter.collect::<Vec<SyntaxNode>>(),
-                                closed_with,
-                            );
+                        let inner = SyntaxTree {
+                            nodes: iter.collect(),
+                            span: Span::new(open_span.file, open_span.line, open_span.end, token.span.start),
+                        };
+                        stack.top_mut()
+                            .ok_or(ParseError::TooManyCloseBrackets { span: token.span })?
+                            .push_group(opened_with, inner, closed_with, open_span.to(token.span));
                     } else {
                         return Err(ParseError::BracketMismatch {
                             opened_with,
+                            open_span,
                             closed_with,
+                            close_span: token.span,
                         });
                     }
                 } else {
-                    panic!("Group should not be created without open & close delimiters");
+                    // Only the root lacks an open delimiter
+                    return Err(ParseError::TooManyCloseBrackets { span: token.span });
                 }
             },
 
@@ -57,90 +63,453 @@ fn group_subexpressions<'doc>(tokens: Vec<Token<'doc>>) -> Result<SyntaxTree<'do
         }
     }
     let result = stack.pop()
-        .ok_or(ParseError::TooManyCloseBrackets)?;
+        .expect("Should have errored already if the root was popped");
 
-    if stack.is_empty() {
-        Ok(result)
-    } else {
-        Err(ParseError::NotEnoughCloseBrackets)
+    match result.nodes.first() {
+        Some(SyntaxNode::Token(Token { kind: TokenKind::GroupCtrl(GroupCtrlToken { ctrl: GroupControl::Open, .. }), span }))
+            if !stack.is_empty() => Err(ParseError::NotEnoughCloseBrackets { span: *span }),
+        _ => Ok(result),
     }
 }
 
-/// Groups operators with their arguments in-place
-fn group_operators<'doc>(tree: &mut SyntaxTree<'doc>) -> Result<(), ParseError> {
-    // DFS
-    for node in tree.0.iter_mut() {
-        if let SyntaxNode::Group{ inner, .. } = node {
-            group_operators(inner)?; // Modify in place
-        }
+/// The nodes of a single tree level that have yet to be grouped with operators.
+type Nodes<'doc> = std::iter::Peekable<std::vec::IntoIter<SyntaxNode<'doc>>>;
+
+/// If the node is an ungrouped operator, get the operator and where it is.
+fn as_operator(node: &SyntaxNode<'_>) -> Option<(OperatorToken, Span)> {
+    match node {
+        SyntaxNode::Token(Token { kind: TokenKind::Operator(op_token), span }) => Some((op_token.clone(), *span)),
+        _ => None,
+    }
+}
+
+/// Whether the node can be the first node of an argument.
+fn starts_argument(node: &SyntaxNode<'_>) -> bool {
+    match as_operator(node) {
+        Some((op_token, _)) => op_token.nary().contains(&(0, 1)),
+        None => true,
     }
+}
 
-    'operator_loop: loop {
-        let operator_tokens: Vec<_> = tree.0
-            .iter()
-            .enumerate()
-            .filter_map(|(i, item)|
-                if let SyntaxNode::Token(Token::Operator(op_token)) = item {
-                    Some((i, op_token))
-                } else {
-                    None
+/// Precedence climbing: parse the longest expression at the front of `nodes` whose operators all bind tighter than `min_power`.
+///
+/// An expression ends early at a node that is not an operator, so that adjacent
+/// expressions (like `f (x)`) are left next to each other rather than erroring.
+fn parse_expression<'doc>(line: &str, nodes: &mut Nodes<'doc>, min_power: usize) -> Result<SyntaxNode<'doc>, ParseError> {
+    let mut lhs = match nodes.next() {
+        Some(node) => match as_operator(&node) {
+            // Prefix
+            Some((op_token, op_span)) if op_token.nary().contains(&(0, 1)) && nodes.peek().is_some_and(starts_argument) => {
+                let (_, right_power) = op_token.infix_power();
+                let rhs = parse_expression(line, nodes, right_power)?;
+                SyntaxNode::Operator {
+                    lhs: Vec::new(),
+                    op: op_token,
+                    op_span,
+                    span: op_span.to(rhs.span()),
+                    rhs: vec![rhs],
                 }
-            )
-            .collect();
+            },
 
-        let mut current_target = match operator_tokens.first() {
-            Some(&(i, op_token)) => (i, op_token.bind_power()),
-            None => break 'operator_loop,
-        };
+            Some((op_token, span)) => return Err(ParseError::OperatorMissingArguments {
+                num_lhs: 0,
+                op_str: span.text(line).to_string(),
+                op_token,
+                num_rhs: nodes.len(),
+                span,
+            }),
 
-        for (i, op_token) in operator_tokens {
-            let bind_power = op_token.bind_power();
-            if bind_power > current_target.1 {
-                current_target = (i, bind_power);
-            }
+            None => node,
+        },
+        None => unreachable!("Expressions should only be parsed while nodes remain"),
+    };
+
+    // The last infix operator grouped at this level, to catch `a op b op c` for non-associative operators
+    let mut prev_infix: Option<(OperatorToken, Span)> = None;
+    while let Some((op_token, op_span)) = nodes.peek().and_then(as_operator) {
+        let nary = op_token.nary();
+        let (left_power, right_power) = op_token.infix_power();
+        if left_power < min_power {
+            break;
         }
-        let (i, _) = current_target;
-
-        if let SyntaxNode::Token(Token::Operator(op_token)) = tree.0[i] {
-            'nary_loop: for (num_lhs, num_rhs) in op_token.nary() {
-                assert!(!(num_lhs == 0 && num_rhs == 0), "operator must take argument(s)");
-                let start = i.checked_sub(num_lhs);
-                let end = i.checked_add(num_rhs);
-                if let (Some(start), Some(end)) = (start, end) {
-                    let lhs = &tree.0[start..i];
-                    let rhs = &tree.0[(i+1)..=end];
-                    if lhs.len() == num_lhs && lhs.len() == num_rhs {
-                        tree.0.splice(start..=end, [
-                            SyntaxNode::Operator {
-                                lhs: lhs.to_vec(),
-                                op: op_token,
-                                rhs: rhs.to_vec(),
-                            }
-                        ]);
-                        continue 'operator_loop;
-                    } else {
-                        continue 'nary_loop;
-                    }
-                } else {
-                    continue 'nary_loop;
+
+        let is_infix = nary.contains(&(1, 1));
+        let is_postfix = nary.contains(&(1, 0));
+        if !is_infix && !is_postfix {
+            // Prefix-only operator; it starts the next expression instead of continuing this one
+            break;
+        }
+        nodes.next();
+
+        lhs = if is_infix && nodes.peek().is_some_and(starts_argument) {
+            if let Some((prev, prev_span)) = prev_infix {
+                let non_assoc = prev.assoc() == Assoc::None || op_token.assoc() == Assoc::None;
+                if non_assoc && prev.precedence() == op_token.precedence() {
+                    return Err(ParseError::NonAssociative {
+                        first: prev_span.text(line).to_string(),
+                        first_span: prev_span,
+                        second: op_span.text(line).to_string(),
+                        span: op_span,
+                    });
                 }
             }
-
-            // Reached end of `'nary_loop` without finding a match
+            prev_infix = Some((op_token.clone(), op_span));
+            let rhs = parse_expression(line, nodes, right_power)?;
+            SyntaxNode::Operator {
+                span: lhs.span().to(rhs.span()),
+                lhs: vec![lhs],
+                op: op_token,
+                op_span,
+                rhs: vec![rhs],
+            }
+        } else if is_postfix {
+            SyntaxNode::Operator {
+                span: lhs.span().to(op_span),
+                lhs: vec![lhs],
+                op: op_token,
+                op_span,
+                rhs: Vec::new(),
+            }
+        } else {
             return Err(ParseError::OperatorMissingArguments {
-                num_lhs: i,
+                num_lhs: 1,
+                op_str: op_span.text(line).to_string(),
                 op_token,
-                num_rhs: tree.0.len() - i - 1,
+                num_rhs: nodes.len(),
+                span: op_span,
             });
+        };
+    }
+
+    Ok(lhs)
+}
+
+/// If the node is a bracket group of the given kind, get its content.
+fn as_group<'node, 'doc>(node: Option<&'node SyntaxNode<'doc>>, kind: BracketKind) -> Option<&'node SyntaxTree<'doc>> {
+    match node {
+        Some(SyntaxNode::Group { open, inner, .. }) if *open == kind => Some(inner),
+        _ => None,
+    }
+}
+
+/// Take the content of the group at the front of the queue.
+fn pop_group<'doc>(nodes: &mut VecDeque<SyntaxNode<'doc>>) -> (SyntaxTree<'doc>, Span) {
+    match nodes.pop_front() {
+        Some(SyntaxNode::Group { inner, span, .. }) => (inner, span),
+        _ => unreachable!("Should have checked for a group before popping it"),
+    }
+}
+
+/// Whether the node is the given operator, not yet grouped with its arguments.
+fn is_operator(node: Option<&SyntaxNode<'_>>, op: OperatorToken) -> bool {
+    node.and_then(as_operator).is_some_and(|(op_token, _)| op_token == op)
+}
+
+/// Take the next node from the queue, combined with any of the nodes after it that belong to it.
+///
+/// Function names are combined with their `[index]`, decorations, and `(arguments)`.
+/// Decorations are primes and exponents that apply to the function itself, like `f'(x)` and `sin^2(x)`.
+/// Only words defined as [`DefKind::Function`](crate::processor::DefKind::Function) are treated as functions.
+///
+/// Big operators are combined with their `[index]` and operand, like `sum[i = 0, n] 2 i^2`.
+/// `after_operand` tells whether an operator like `union` is between two operands rather than heading a big operator.
+fn next_primary<'doc>(line: &str, nodes: &mut VecDeque<SyntaxNode<'doc>>, after_operand: bool) -> Result<Option<SyntaxNode<'doc>>, ParseError> {
+    let Some(node) = nodes.pop_front() else {
+        return Ok(None);
+    };
+    match node {
+        SyntaxNode::Token(Token { kind: TokenKind::Word(function), span }) if function.kind() == Some(DefKind::Function) => {
+            // Look ahead for `[index]' ^power (args)`, where everything but `(args)` is optional
+            let has_index = as_group(nodes.front(), BracketKind::Brack).is_some();
+            let mut at = has_index as usize;
+            let mut primes = 0;
+            while is_operator(nodes.get(at), OperatorToken::Prime) {
+                primes += 1;
+                at += 1;
+            }
+            // The exponent may be signed, as in `sin^-1(x)`
+            let has_sign = [OperatorToken::Minus, OperatorToken::Plus, OperatorToken::Pm].into_iter()
+                .any(|sign| is_operator(nodes.get(at + 1), sign));
+            let has_power = is_operator(nodes.get(at), OperatorToken::Superscript)
+                && nodes.get(at + 1 + has_sign as usize).is_some_and(|power| as_operator(power).is_none());
+            if has_power {
+                at += 2 + has_sign as usize;
+            }
+
+            if as_group(nodes.get(at), BracketKind::Paren).is_none() {
+                return Ok(Some(node));
+            }
+
+            let subscript = has_index.then(|| pop_group(nodes).0);
+            nodes.drain(..primes);
+            let superscript = has_power.then(|| {
+                nodes.pop_front();
+                let sign = has_sign.then(|| nodes.pop_front().as_ref().and_then(as_operator)).flatten();
+                let power = nodes.pop_front()
+                    .expect("Should have checked for an exponent before popping it");
+                match sign {
+                    Some((op, sign_span)) => SyntaxNode::Operator {
+                        lhs: Vec::new(),
+                        op,
+                        op_span: sign_span,
+                        span: sign_span.to(power.span()),
+                        rhs: vec![power],
+                    }.extract_inner(),
+                    None => power.extract_inner(),
+                }
+            });
+            let (args, args_span) = pop_group(nodes);
+            Ok(Some(SyntaxNode::Apply {
+                function,
+                subscript,
+                primes,
+                superscript,
+                args,
+                span: span.to(args_span),
+            }))
+        },
+
+        SyntaxNode::Token(Token { kind: TokenKind::BigOp(op), span }) => {
+            group_big_op(line, nodes, op, span).map(Some)
+        },
+
+        SyntaxNode::Token(Token { kind: TokenKind::Operator(ref op_token), span }) if !after_operand => {
+            // `union[...]` is a big operator, but `A union [...]` is not
+            match BigOpToken::from_operator(op_token) {
+                Some(op) if as_group(nodes.front(), BracketKind::Brack).is_some()
+                    => group_big_op(line, nodes, op, span).map(Some),
+                _ => Ok(Some(node)),
+            }
+        },
+
+        _ => Ok(Some(node)),
+    }
+}
+
+/// Combine a big operator with the `[index]` and operand following it.
+///
+/// The operand is a whole term: everything up to the next operator that binds more loosely than `^`,
+/// so that `sum[i = 0, n] 2 i^2 + 1` sums `2 i^2` and then adds `1`.
+/// An operand in parentheses is the whole operand, as it is for a function, so `sqrt(x)^2` squares the root.
+fn group_big_op<'doc>(line: &str, nodes: &mut VecDeque<SyntaxNode<'doc>>, op: BigOpToken, span: Span) -> Result<SyntaxNode<'doc>, ParseError> {
+    let index = as_group(nodes.front(), BracketKind::Brack).is_some().then(|| pop_group(nodes));
+    let term = match as_group(nodes.front(), BracketKind::Paren) {
+        Some(_) => Vec::from_iter(nodes.pop_front()),
+        None => next_term(line, nodes)?,
+    };
+    if term.is_empty() {
+        return Err(ParseError::MissingOperand { span: index.map_or(span, |(_, index_span)| span.to(index_span)) });
+    }
+
+    let mut term = term.into_iter().peekable();
+    let mut body = SyntaxTree::new();
+    while term.peek().is_some() {
+        body.push(parse_expression(line, &mut term, 0)?);
+    }
+    Ok(SyntaxNode::BigOp {
+        op,
+        index: index.map(|(index, _)| index),
+        op_span: span,
+        span: span.to(body.span),
+        body,
+    })
+}
+
+/// Take the nodes of the term at the front of the queue: operands multiplied by being written next to each other,
+/// with any signs before them and any operators that bind at least as tightly as `^` after them, like `-2 x_1^2 n!`.
+fn next_term<'doc>(line: &str, nodes: &mut VecDeque<SyntaxNode<'doc>>) -> Result<Vec<SyntaxNode<'doc>>, ParseError> {
+    let tightest = OperatorToken::Superscript.bind_power();
+    let mut term = Vec::new();
+    let mut expects_operand = true;
+    while let Some(front) = nodes.front() {
+        match as_operator(front) {
+            Some((op_token, _)) if expects_operand => {
+                if !op_token.nary().contains(&(0, 1)) {
+                    break;
+                }
+            },
+            Some((op_token, _)) => {
+                let nary = op_token.nary();
+                if op_token.bind_power() < tightest || !(nary.contains(&(1, 1)) || nary.contains(&(1, 0))) {
+                    break;
+                }
+                expects_operand = nary.contains(&(1, 1));
+            },
+            None => {
+                let after_operand = !expects_operand;
+                term.extend(next_primary(line, nodes, after_operand)?);
+                expects_operand = false;
+                continue;
+            },
+        }
+        term.extend(nodes.pop_front());
+    }
+    Ok(term)
+}
+
+/// Groups function applications and big operators with their arguments in-place
+fn group_primaries(line: &str, tree: &mut SyntaxTree<'_>) -> Result<(), ParseError> {
+    let mut nodes = VecDeque::from(std::mem::take(&mut tree.nodes));
+    loop {
+        let after_operand = tree.nodes.last().is_some_and(|prev| as_operator(prev).is_none());
+        match next_primary(line, &mut nodes, after_operand)? {
+            Some(node) => tree.nodes.push(node),
+            None => break,
+        }
+    }
+    Ok(())
+}
+
+/// Groups operators with their arguments in-place
+fn group_operators<'doc>(line: &str, tree: &mut SyntaxTree<'doc>) -> Result<(), ParseError> {
+    // DFS
+    for node in tree.nodes.iter_mut() {
+        if let SyntaxNode::Group{ inner, .. } = node {
+            group_operators(line, inner)?; // Modify in place
         }
-        break;
+    }
+
+    group_primaries(line, tree)?;
+
+    let mut nodes = std::mem::take(&mut tree.nodes).into_iter().peekable();
+    while nodes.peek().is_some() {
+        let node = parse_expression(line, &mut nodes, 0)?;
+        tree.nodes.push(node);
     }
     Ok(())
 }
 
 /// Apply clumping and lookaround to the document.
-pub fn parse<'doc>(tokens: Vec<Token<'doc>>) -> Result<SyntaxTree<'doc>, ParseError> {
+///
+/// `line` is the text the tokens were lexed from, so that errors can quote operators as they were written.
+pub fn parse<'doc>(line: &str, tokens: Vec<Token<'doc>>) -> Result<SyntaxTree<'doc>, ParseError> {
     let mut tree = group_subexpressions(tokens)?;
-    group_operators(&mut tree)?;
+    group_operators(line, &mut tree)?;
     Ok(tree)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::LazyLock;
+
+    use super::*;
+    use crate::to_tex::ToTex;
+
+    /// A lexer with only the builtin notation.
+    static LEXER: LazyLock<Lexer> = LazyLock::new(Lexer::new);
+
+    /// Parse a line with only the builtin notation.
+    fn parse_line(line: &str) -> Result<SyntaxTree<'_>, ParseError> {
+        let (tokens, errors) = LEXER.tokenize(line, 0, 1);
+        assert!(errors.is_empty(), "{line:?} should lex without errors");
+        parse(line, tokens)
+    }
+
+    /// Write a node as an s-expression, with `_` for a missing side, like `(- _ a)` for negation.
+    fn shape(node: &SyntaxNode<'_>) -> String {
+        match node {
+            SyntaxNode::Token(token) => match &token.kind {
+                TokenKind::Word(word) => word.command(),
+                TokenKind::Number(number) => number.to_string(),
+                kind => format!("{kind:?}"),
+            },
+            SyntaxNode::Operator { lhs, op, rhs, .. } => {
+                let side = |nodes: &[SyntaxNode<'_>]| match nodes {
+                    [] => "_".to_string(),
+                    nodes => nodes.iter().map(shape).collect::<Vec<_>>().join(" "),
+                };
+                format!("({} {} {})", op.source_str(), side(lhs), side(rhs))
+            },
+            SyntaxNode::Group { inner, .. } => format!("[{}]", inner.nodes.iter().map(shape).collect::<Vec<_>>().join(" ")),
+            SyntaxNode::BigOp { op, body, .. } => format!("({} {})", op.source_str(), body.nodes.iter().map(shape).collect::<Vec<_>>().join(" ")),
+            node => format!("{node:?}"),
+        }
+    }
+
+    /// The shape of each top-level expression on the line.
+    fn shapes(line: &str) -> Vec<String> {
+        let tree = parse_line(line).unwrap_or_else(|error| panic!("{line:?} should parse: {error}"));
+        tree.nodes.iter().map(shape).collect()
+    }
+
+    #[test]
+    fn infix() {
+        assert_eq!(shapes("a + b * c"), ["(+ a (* b c))"]);
+        assert_eq!(shapes("a * b + c"), ["(+ (* a b) c)"]);
+        assert_eq!(shapes("a - b - c"), ["(- (- a b) c)"]);
+        assert_eq!(shapes("a ^ b ^ c"), ["(^ a (^ b c))"]);
+        assert_eq!(shapes("a < b < c"), ["(< (< a b) c)"]);
+    }
+
+    #[test]
+    fn non_associative() {
+        assert!(matches!(parse_line("a choose b choose c"), Err(ParseError::NonAssociative { .. })));
+        assert_eq!(shapes("(a choose b) choose c"), ["(choose [(choose a b)] c)"]);
+    }
+
+    #[test]
+    fn errors_quote_operators_as_written() {
+        let message = |line| parse_line(line).map(|_| ()).unwrap_err().to_string();
+        assert_eq!(message("0 ..= 1 .. 2"), "`..=` and `..` cannot be used in a row without parentheses");
+        assert_eq!(message("a =="), "No version of the `==` operator takes 1 left-hand arguments and 0 right-hand arguments.");
+    }
+
+    #[test]
+    fn prefix() {
+        assert_eq!(shapes("not a"), ["(not _ a)"]);
+        assert_eq!(shapes("not a and b"), ["(and (not _ a) b)"]);
+        assert_eq!(shapes("..n"), ["(.. _ n)"]);
+    }
+
+    #[test]
+    fn postfix() {
+        assert_eq!(shapes("n!"), ["(! n _)"]);
+        assert_eq!(shapes("n! + 1"), ["(+ (! n _) 1)"]);
+        assert_eq!(shapes("a' * b"), ["(* (' a _) b)"]);
+        assert_eq!(shapes("0.."), ["(.. 0 _)"]);
+    }
+
+    #[test]
+    fn unary_signs() {
+        assert_eq!(shapes("-a"), ["(- _ a)"]);
+        assert_eq!(shapes("+a"), ["(+ _ a)"]);
+        assert_eq!(shapes("+/-a"), ["(+/- _ a)"]);
+        assert_eq!(shapes("-a + b"), ["(+ (- _ a) b)"]);
+        assert_eq!(shapes("a - -b"), ["(- a (- _ b))"]);
+        assert_eq!(shapes("a * -b"), ["(* a (- _ b))"]);
+        assert_eq!(shapes("a +/- b"), ["(+/- a b)"]);
+    }
+
+    #[test]
+    fn unary_tex() {
+        assert_eq!(parse_line("-a").unwrap().to_tex(), r"\op{-}{{a}}");
+        assert_eq!(parse_line("a - b").unwrap().to_tex(), r"{{a}}\op{-}{{b}}");
+        assert_eq!(parse_line("+/-a").unwrap().to_tex(), r"\op{\pm}{{a}}");
+    }
+
+    #[test]
+    fn function_powers() {
+        assert_eq!(parse_line("cos^2(theta)").unwrap().to_tex(), r"\fn{\cos^{\lit{2}}}{{\br{\lparen}{\var{\theta}}{\rparen}}}");
+        assert_eq!(parse_line("sin^-1(x)").unwrap().to_tex(), r"\fn{\sin^{\op{-}{\lit{1}}}}{{\br{\lparen}{{x}}{\rparen}}}");
+        assert_eq!(parse_line("sin^+/-2(x)").unwrap().to_tex(), r"\fn{\sin^{\op{\pm}{\lit{2}}}}{{\br{\lparen}{{x}}{\rparen}}}");
+        // A sign without an exponent after it isn't a power
+        assert_eq!(shapes("sin^-(x)").len(), 1);
+    }
+
+    #[test]
+    fn big_op_operands() {
+        assert_eq!(shapes("sum[i = 0, n] i^2"), ["(sum (^ i 2))"]);
+        assert_eq!(shapes("sum[i = 0, n] 2 i"), ["(sum 2 i)"]);
+        assert_eq!(shapes("sum[i = 0, n] 2 i^2 + 1"), ["(+ (sum 2 (^ i 2)) 1)"]);
+        assert_eq!(shapes("sum[i = 0, n] -i"), ["(sum (- _ i))"]);
+        assert_eq!(shapes("sqrt(x)^2"), ["(^ (sqrt [x]) 2)"]);
+        assert!(matches!(parse_line("sum[i = 0, n]"), Err(ParseError::MissingOperand { .. })));
+        assert!(matches!(parse_line("sum[i = 0, n] = 1"), Err(ParseError::MissingOperand { .. })));
+    }
+
+    #[test]
+    fn missing_arguments() {
+        assert!(matches!(parse_line("a +"), Err(ParseError::OperatorMissingArguments { .. })));
+        assert!(matches!(parse_line("* a"), Err(ParseError::OperatorMissingArguments { .. })));
+    }
+}
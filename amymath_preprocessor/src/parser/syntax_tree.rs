@@ -1,7 +1,7 @@
 #![allow(dead_code)]
 use std::fmt::Debug;
 
-use crate::{to_tex::ToTex, lexer::*};
+use crate::{to_tex::ToTex, lexer::*, processor::DefKind};
 
 /// A node in a token tree.
 #[derive(Clone)]
@@ -19,6 +19,51 @@ pub enum SyntaxNode<'doc> {
 
         /// Right hand side arguments
         rhs: Vec<SyntaxNode<'doc>>,
+
+        /// Where the operator itself is written
+        op_span: Span,
+
+        /// The region spanning the operator and all of its arguments
+        span: Span,
+    },
+
+    /// A function applied to arguments, like `log[n](x)`
+    Apply {
+        /// The function being applied
+        function: WordToken<'doc>,
+
+        /// The index written in brackets after the function name, like the base of a logarithm
+        subscript: Option<SyntaxTree<'doc>>,
+
+        /// The number of primes (derivatives) written after the function name, like `f''(x)`
+        primes: usize,
+
+        /// The exponent applied to the function itself rather than its result, like `sin^2(x)`
+        superscript: Option<SyntaxTree<'doc>>,
+
+        /// The content within the parentheses
+        args: SyntaxTree<'doc>,
+
+        /// The region spanning the function name through the closing parenthesis
+        span: Span,
+    },
+
+    /// An operator like a summation or root, applied to its operand
+    BigOp {
+        /// The operator
+        op: BigOpToken,
+
+        /// The content of the brackets after the operator, like the bounds of a summation
+        index: Option<SyntaxTree<'doc>>,
+
+        /// The operand, which may be several factors multiplied together, like `2 i^2`
+        body: SyntaxTree<'doc>,
+
+        /// Where the operator itself is written
+        op_span: Span,
+
+        /// The region spanning the operator through its operand
+        span: Span,
     },
 
     /// A subtree
@@ -31,6 +76,9 @@ pub enum SyntaxNode<'doc> {
 
         /// Implied to be [`GroupControl::Close`].
         close: BracketKind,
+
+        /// The region spanning both brackets and the content between them
+        span: Span,
     },
 }
 
@@ -40,18 +88,40 @@ impl<'doc> Debug for SyntaxNode<'doc> {
             Self::Token(arg0)
                 => write!(f, "Token({arg0:?})"), // Ensure non-pretty debug
 
-            Self::Operator { lhs, op, rhs }
+            Self::Operator { lhs, op, rhs, op_span, span }
                 => f.debug_struct("BinOp")
                     .field("lhs", lhs)
                     .field("op", op)
                     .field("rhs", rhs)
+                    .field("op_span", &format_args!("{op_span}"))
+                    .field("span", &format_args!("{span}"))
+                    .finish(),
+
+            Self::Apply { function, subscript, primes, superscript, args, span }
+                => f.debug_struct("Apply")
+                    .field("function", function)
+                    .field("subscript", subscript)
+                    .field("primes", primes)
+                    .field("superscript", superscript)
+                    .field("args", args)
+                    .field("span", &format_args!("{span}"))
                     .finish(),
 
-            Self::Group{ open, inner: subtree, close }
+            Self::BigOp { op, index, body, op_span, span }
+                => f.debug_struct("BigOp")
+                    .field("op", op)
+                    .field("index", index)
+                    .field("body", body)
+                    .field("op_span", &format_args!("{op_span}"))
+                    .field("span", &format_args!("{span}"))
+                    .finish(),
+
+            Self::Group{ open, inner: subtree, close, span }
                 => f.debug_tuple("Group")
                     .field(open)
                     .field(subtree)
                     .field(close)
+                    .field(&format_args!("{span}"))
                     .finish(),
         }
     }
@@ -59,26 +129,41 @@ impl<'doc> Debug for SyntaxNode<'doc> {
 
 impl<'doc> SyntaxNode<'doc> {
     /// Construct a SyntaxNode representing a single Token and nothing else.
-    pub fn new_token(token: Token<'doc>) -> Self {
+    pub(crate) fn new_token(token: Token<'doc>) -> Self {
         Self::Token(token)
     }
 
     /// Construct a SyntaxNode representing a delimited subtree.
-    pub fn new_group() -> Self {
+    pub(crate) fn new_group() -> Self {
         Self::Group{
             open: BracketKind::Blank,
             inner: SyntaxTree::new(),
             close: BracketKind::Blank,
+            span: Span::default(),
+        }
+    }
+
+    /// The region of the source document this node was built from.
+    pub fn span(&self) -> Span {
+        match self {
+            SyntaxNode::Token(token) => token.span,
+            SyntaxNode::Operator { span, .. } => *span,
+            SyntaxNode::Apply { span, .. } => *span,
+            SyntaxNode::BigOp { span, .. } => *span,
+            SyntaxNode::Group { span, .. } => *span,
         }
     }
 
     /// If the node within a parenthetical `()` group, get the TeX of the contents of that group without the parentheses.
-    pub fn extract_inner(self) -> SyntaxTree<'doc> {
+    pub(crate) fn extract_inner(self) -> SyntaxTree<'doc> {
         match self {
-            SyntaxNode::Group{ open: BracketKind::Paren, inner, close: BracketKind::Paren }
+            SyntaxNode::Group{ open: BracketKind::Paren, inner, close: BracketKind::Paren, .. }
                 => inner,
 
-            _ => SyntaxTree(vec![self]),
+            _ => {
+                let span = self.span();
+                SyntaxTree { nodes: vec![self], span }
+            },
         }
     }
 }
@@ -89,10 +174,55 @@ impl<'doc> ToTex for SyntaxNode<'doc> {
             SyntaxNode::Token(token)
                 => token.to_tex().to_owned(),
 
-            SyntaxNode::Operator{ lhs, op, rhs } =>
+            SyntaxNode::Operator{ lhs, op, rhs, .. } =>
                 op.format(lhs, rhs),
 
-            SyntaxNode::Group{ open, inner, close }
+            SyntaxNode::Apply{ function, subscript, primes, superscript, args, .. } => {
+                let mut name = function.command();
+                if let Some(subscript) = subscript {
+                    name = format!("{name}_{{{}}}", subscript.to_tex());
+                }
+                if primes > 0 {
+                    name = format!("{name}^{{{}}}", OperatorToken::Prime.to_tex().repeat(primes));
+                }
+                if let Some(superscript) = superscript {
+                    // Wrapped so that the exponent doesn't collide with the primes
+                    name = if primes > 0 { format!("{{{name}}}") } else { name };
+                    name = format!("{name}^{{{}}}", superscript.to_tex());
+                }
+                format!("{}{{{name}}}{{{}}}",
+                    DefKind::Function.to_tex(),
+                    SyntaxNode::Group {
+                        open: BracketKind::Paren,
+                        inner: args,
+                        close: BracketKind::Paren,
+                        span: Span::default(),
+                    }.to_tex(),
+                )
+            },
+
+            SyntaxNode::BigOp{ op, index, body, .. } => match op.form() {
+                BigOpForm::Root => {
+                    let degree = index
+                        .map(|index| format!("[{}]", index.to_tex()))
+                        .unwrap_or_default();
+                    format!("{}{degree}{{{}}}", op.command(), body.extract_inner().to_tex())
+                },
+                BigOpForm::Bounds => {
+                    let bounds = index
+                        .map(|index| {
+                            let (lower, upper) = index.into_bounds();
+                            let upper = upper
+                                .map(|upper| format!("^{{{upper}}}"))
+                                .unwrap_or_default();
+                            format!("_{{{lower}}}{upper}")
+                        })
+                        .unwrap_or_default();
+                    format!("{}{bounds} {}", op.command(), body.to_tex())
+                },
+            },
+
+            SyntaxNode::Group{ open, inner, close, .. }
                 => format!("{}{}{}",
                     GroupCtrlToken::open(open).to_tex(),
                     inner.to_tex(),
@@ -103,38 +233,133 @@ impl<'doc> ToTex for SyntaxNode<'doc> {
 }
 
 /// A collection adapter for a [`Vec<SyntaxNode>`] with methods for pushing particular types of nodes.
-#[derive(Clone)]
-pub struct SyntaxTree<'doc>(pub Vec<SyntaxNode<'doc>>);
+#[derive(Clone, Default)]
+pub struct SyntaxTree<'doc> {
+    /// The nodes of the tree, in source order.
+    pub nodes: Vec<SyntaxNode<'doc>>,
+    /// The region of the source document covered by the tree.
+    pub span: Span,
+}
 
 impl<'doc> Debug for SyntaxTree<'doc> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_list().entries(self.0.iter()).finish()
+        f.debug_list().entries(self.nodes.iter()).finish()
     }
 }
 
 impl<'doc> SyntaxTree<'doc> {
     /// Construct an empty tree.
-    pub fn new() -> Self {
-        Self(Vec::new())
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a node to the tree, growing the span of the tree to include it.
+    pub(crate) fn push(&mut self, node: SyntaxNode<'doc>) {
+        let span = node.span();
+        self.span = if self.nodes.is_empty() { span } else { self.span.to(span) };
+        self.nodes.push(node);
     }
 
     /// Add a delimited subtree to the tree.
-    pub fn push_group(&mut self, open: BracketKind, inner: Vec<SyntaxNode<'doc>>, close: BracketKind) {
-        self.0.push(SyntaxNode::Group { open, inner: Self(inner), close });
+    pub(crate) fn push_group(&mut self, open: BracketKind, inner: SyntaxTree<'doc>, close: BracketKind, span: Span) {
+        self.push(SyntaxNode::Group { open, inner, close, span });
     }
 
     /// Add a [`Token`] to the tree.
-    pub fn push_token(&mut self, token: Token<'doc>) {
-        self.0.push(SyntaxNode::Token(token));
+    pub(crate) fn push_token(&mut self, token: Token<'doc>) {
+        self.push(SyntaxNode::Token(token));
+    }
+}
+
+impl<'doc> SyntaxTree<'doc> {
+    /// If the tree is only a parenthetical `()` group, get the contents of that group without the parentheses.
+    pub(crate) fn extract_inner(mut self) -> SyntaxTree<'doc> {
+        match self.nodes.pop() {
+            Some(node) if self.nodes.is_empty() => node.extract_inner(),
+            node => {
+                self.nodes.extend(node);
+                self
+            },
+        }
+    }
+
+    /// Interpret the tree as the index of a [`BigOpForm::Bounds`] operator, and convert the lower and upper bounds to TeX.
+    ///
+    /// `i = 0, n` and `i in 0..n` have a lower bound of `i = 0` and an upper bound of `n`, and `i in 0..<n` has an upper bound of `n - 1`.
+    /// Anything else is a lower bound on its own, like the `x in S` of `\sum_{x \in S}`.
+    fn into_bounds(self) -> (String, Option<String>) {
+        match &self.nodes[..] {
+            [SyntaxNode::Operator { op: OperatorToken::In, lhs, rhs, .. }]
+                if lhs.len() == 1 && matches!(&rhs[..], [SyntaxNode::Operator { op: OperatorToken::Range | OperatorToken::RangeExclusive, lhs, .. }] if lhs.len() == 1) =>
+            {
+                let Some(SyntaxNode::Operator { lhs: index, rhs, .. }) = self.nodes.into_iter().next() else {
+                    unreachable!("Pattern was already matched")
+                };
+                let Some(SyntaxNode::Operator { lhs: lower, op: range, rhs: upper, span, .. }) = rhs.into_iter().next() else {
+                    unreachable!("Pattern was already matched")
+                };
+                let lower = OperatorToken::Eq.format(index, lower);
+                let upper = match (range, upper.is_empty()) {
+                    (_, true) => r"\infty".to_string(),
+                    (OperatorToken::RangeExclusive, false) => OperatorToken::Minus.format(upper, vec![
+                        SyntaxNode::Token(Token::new(TokenKind::Number("1"), span)),
+                    ]),
+                    (_, false) => SyntaxTree { nodes: upper, span }.to_tex(),
+                };
+                (lower, Some(upper))
+            },
+            [SyntaxNode::Operator { op: OperatorToken::Comma, .. }] => {
+                let Some(SyntaxNode::Operator { lhs, rhs, .. }) = self.nodes.into_iter().next() else {
+                    unreachable!("Pattern was already matched")
+                };
+                let [lower, upper] = [lhs, rhs].map(|nodes| SyntaxTree { nodes, span: Span::default() }.to_tex());
+                (lower, Some(upper))
+            },
+            _ => (self.to_tex(), None),
+        }
     }
 }
 
 impl<'doc> ToTex for SyntaxTree<'doc> {
     fn to_tex(self) -> String {
-        self.0
+        self.nodes
             .into_iter()
             .map(|node| node.to_tex())
             .collect::<Vec<String>>()
             .join(" ")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    /// The TeX of the bounds of the index group at the start of `line`.
+    fn bounds(line: &str) -> (String, Option<String>) {
+        let lexer = Lexer::new();
+        let (tokens, _) = lexer.tokenize(line, 0, 1);
+        let tree = parse(line, tokens).unwrap_or_else(|error| panic!("{line:?} should parse: {error}"));
+        match tree.nodes.into_iter().next() {
+            Some(SyntaxNode::Group { inner, .. }) => inner.into_bounds(),
+            node => panic!("{line:?} should start with a group, not {node:?}"),
+        }
+    }
+
+    #[test]
+    fn range_bounds() {
+        let lower = r"{{i}}\stmt{=}{\lit{0}}".to_string();
+        // `..` is inclusive, like `..=`
+        assert_eq!(bounds("[i in 0..n]"), (lower.clone(), Some("{n}".to_string())));
+        assert_eq!(bounds("[i in 0..=n]"), (lower.clone(), Some("{n}".to_string())));
+        // `..<` stops one short of the upper bound
+        assert_eq!(bounds("[i in 0..<n]"), (lower.clone(), Some(r"{{n}}\op{-}{\lit{1}}".to_string())));
+        assert_eq!(bounds("[i in 0..]"), (lower, Some(r"\infty".to_string())));
+    }
+
+    #[test]
+    fn other_bounds() {
+        assert_eq!(bounds("[i = 0, n]"), (r"{{i}}\stmt{=}{\lit{0}}".to_string(), Some("{n}".to_string())));
+        assert_eq!(bounds("[x in S]"), (r"{{x}}\stmt{\in}{{S}}".to_string(), None));
+    }
+}
@@ -0,0 +1,337 @@
+use std::{collections::{HashMap, HashSet}, hash::{Hash, Hasher}, io::{BufRead, Read}, path::Path};
+
+use crate::lexer::{Notation, UnknownChars};
+use super::{definition::Definition, lint::LintLevel};
+
+/// The number bumped when the layout of a saved cache changes.
+const CACHE_FORMAT: u32 = 2;
+
+/// The source of everything that decides the TeX of a line of math, or whether it renders without diagnostics.
+///
+/// A new file in the lexer, parser, checks, or TeX output belongs here, so that changing it invalidates saved caches.
+const RENDERER_SOURCES: &[&str] = &[
+    include_str!("../lexer/mod.rs"),
+    include_str!("../lexer/big_op.rs"),
+    include_str!("../lexer/group_ctrl.rs"),
+    include_str!("../lexer/notation.rs"),
+    include_str!("../lexer/operator.rs"),
+    include_str!("../lexer/span.rs"),
+    include_str!("../lexer/token.rs"),
+    include_str!("../lexer/trie.rs"),
+    include_str!("../lexer/word/mod.rs"),
+    include_str!("../lexer/word/builtin.rs"),
+    include_str!("../lexer/word/direct.rs"),
+    include_str!("../parser/mod.rs"),
+    include_str!("../parser/syntax_tree.rs"),
+    include_str!("../stack.rs"),
+    include_str!("../to_tex.rs"),
+    include_str!("mod.rs"),
+    include_str!("definition.rs"),
+    include_str!("notation.rs"),
+    include_str!("scope.rs"),
+    include_str!("semantics/mod.rs"),
+    include_str!("lint/mod.rs"),
+];
+
+/// The first line of a saved cache. Caches saved with a different header are ignored, since their TeX may be out of date.
+///
+/// The header holds a hash of [`RENDERER_SOURCES`], so a cache is never reused by a build that renders differently.
+fn cache_header() -> String {
+    let mut hasher = StableHasher::default();
+    RENDERER_SOURCES.hash(&mut hasher);
+    format!("amymath line cache, format {CACHE_FORMAT}, renderer {:016x}", hasher.finish())
+}
+
+/// FNV-1a, used instead of [`std::collections::hash_map::DefaultHasher`] so that keys stay the same between runs.
+struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Identifies the definitions, notation, and options that lines of math are rendered with.
+///
+/// These only change at directives, so the key is computed once and shared by the lines of math that follow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ContextKey(u64);
+
+impl ContextKey {
+    /// Combine the definitions, notation, and options in effect.
+    ///
+    /// Where a definition was made doesn't change the TeX, so only its name, kind, and set are part of the key.
+    pub fn new(
+        definitions: &HashMap<&str, Definition<'_>>,
+        notation: &Notation,
+        undefined_names: LintLevel,
+        unknown_chars: UnknownChars,
+    ) -> Self {
+        let mut definitions: Vec<_> = definitions.iter()
+            .map(|(name, definition)| (*name, definition.kind, definition.set))
+            .collect();
+        definitions.sort_unstable_by_key(|(name, _, _)| *name);
+
+        let mut hasher = StableHasher::default();
+        definitions.hash(&mut hasher);
+        notation.hash(&mut hasher);
+        undefined_names.hash(&mut hasher);
+        unknown_chars.hash(&mut hasher);
+        Self(hasher.finish())
+    }
+}
+
+/// The error for a cache entry that can't be read.
+fn invalid_entry() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, "malformed line cache entry")
+}
+
+/// Read `len` bytes of text followed by a newline.
+fn read_text(reader: &mut impl Read, len: usize) -> std::io::Result<String> {
+    let mut text = vec![0; len + 1];
+    reader.read_exact(&mut text)?;
+    if text.pop() != Some(b'\n') {
+        return Err(invalid_entry());
+    }
+    String::from_utf8(text).map_err(|_| invalid_entry())
+}
+
+/// Identifies everything that a line of math's TeX depends on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct LineKey(u64);
+
+impl LineKey {
+    /// Combine the text of the line with the context it is rendered in.
+    fn new(line: &str, context: ContextKey) -> Self {
+        let mut hasher = StableHasher::default();
+        line.hash(&mut hasher);
+        context.hash(&mut hasher);
+        Self(hasher.finish())
+    }
+}
+
+/// A line of math and the TeX it was rendered as.
+///
+/// The line and its context are kept alongside the TeX, so that two lines whose keys collide are never mistaken for each other.
+#[derive(Debug, Clone)]
+struct Entry {
+    line: String,
+    context: ContextKey,
+    tex: String,
+}
+
+/// TeX rendered for lines of math, so that lines which haven't changed are not lexed, parsed, or checked again.
+///
+/// Only lines that rendered without any errors or warnings are kept, so that diagnostics are always reported in full.
+#[derive(Debug, Clone, Default)]
+pub struct LineCache {
+    entries: HashMap<LineKey, Entry>,
+    /// Keys that were looked up or added since the cache was created or loaded.
+    used: HashSet<LineKey>,
+    hits: usize,
+    misses: usize,
+}
+
+impl LineCache {
+    /// Construct an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The TeX of a line that was rendered before in the same context.
+    pub(crate) fn get(&mut self, line: &str, context: ContextKey) -> Option<&str> {
+        let key = LineKey::new(line, context);
+        match self.entries.get(&key).filter(|entry| entry.line == line && entry.context == context) {
+            Some(entry) => {
+                self.hits += 1;
+                self.used.insert(key);
+                Some(&entry.tex)
+            },
+            None => {
+                self.misses += 1;
+                None
+            },
+        }
+    }
+
+    /// Remember the TeX of a line rendered in `context`.
+    pub(crate) fn insert(&mut self, line: &str, context: ContextKey, tex: String) {
+        let key = LineKey::new(line, context);
+        self.used.insert(key);
+        self.entries.insert(key, Entry { line: line.to_string(), context, tex });
+    }
+
+    /// The number of lookups that found a line, and the number that didn't.
+    pub fn stats(&self) -> (usize, usize) {
+        (self.hits, self.misses)
+    }
+
+    /// Forget lines that haven't been used since the cache was loaded, so that it doesn't grow with every edit.
+    pub fn prune(&mut self) {
+        self.entries.retain(|key, _| self.used.contains(key));
+        self.used.clear();
+        self.hits = 0;
+        self.misses = 0;
+    }
+
+    /// Read a cache saved with [`LineCache::save`]. A missing file or one saved by another version gives an empty cache.
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(error) => return Err(error),
+        };
+        let mut reader = std::io::BufReader::new(file);
+        let mut cache = Self::new();
+
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+        if header.trim_end() != cache_header() {
+            return Ok(cache);
+        }
+
+        // Each entry is a line with the context key and the lengths of the line of math and its TeX, then each of those on a line
+        let mut entry = String::new();
+        while { entry.clear(); reader.read_line(&mut entry)? } != 0 {
+            let mut fields = entry.split_whitespace();
+            let mut field = || fields.next().ok_or_else(invalid_entry);
+            let context = u64::from_str_radix(field()?, 16).map_err(|_| invalid_entry())?;
+            let line_len = field()?.parse::<usize>().map_err(|_| invalid_entry())?;
+            let tex_len = field()?.parse::<usize>().map_err(|_| invalid_entry())?;
+            let line = read_text(&mut reader, line_len)?;
+            let tex = read_text(&mut reader, tex_len)?;
+            cache.insert(&line, ContextKey(context), tex);
+        }
+        cache.used.clear();
+        Ok(cache)
+    }
+
+    /// Write the cache to a file, replacing it.
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let mut contents = format!("{}\n", cache_header());
+        for Entry { line, context: ContextKey(context), tex } in self.entries.values() {
+            contents += &format!("{context:016x} {} {}\n{line}\n{tex}\n", line.len(), tex.len());
+        }
+        std::fs::write(path, contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{lexer::span::Span, Processor, SourceMap};
+    use super::*;
+    use super::super::{DefKind, definition::SetKind};
+
+    /// The key for `definitions` with no notation and the default options.
+    fn context(definitions: &[(&'static str, DefKind, Option<SetKind<'static>>, usize)]) -> ContextKey {
+        let definitions = definitions.iter()
+            .map(|&(name, kind, set, line)| (name, Definition { kind, set, span: Span { line, ..Span::default() } }))
+            .collect();
+        ContextKey::new(&definitions, &Notation::default(), LintLevel::default(), UnknownChars::default())
+    }
+
+    /// A path for a saved cache, unique to `test`.
+    fn cache_path(test: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("amymath_cache_{}_{test}", std::process::id()))
+    }
+
+    /// Process `document` with `cache`, giving the number of lines that were reused and rendered.
+    fn process(document: &str, cache: &mut LineCache) -> (usize, usize) {
+        let mut sources = SourceMap::new(PathBuf::from("test.math"), document.to_string());
+        Processor::new().process_cached(&mut sources, cache).unwrap();
+        let stats = cache.stats();
+        cache.prune();
+        stats
+    }
+
+    #[test]
+    fn context_keys() {
+        let x = ("x", DefKind::Variable, None, 1);
+        let y = ("y", DefKind::Variable, Some(SetKind::Real), 2);
+        assert_eq!(context(&[x, y]), context(&[y, x]));
+        // Moving a definition doesn't change its TeX
+        assert_eq!(context(&[x, y]), context(&[("x", DefKind::Variable, None, 5), y]));
+
+        assert_ne!(context(&[x]), context(&[x, y]));
+        assert_ne!(context(&[x]), context(&[("x", DefKind::Constant, None, 1)]));
+        assert_ne!(context(&[y]), context(&[("y", DefKind::Variable, Some(SetKind::Integer), 2)]));
+
+        let definitions = HashMap::new();
+        let key = |notation: &Notation, undefined_names, unknown_chars| ContextKey::new(&definitions, notation, undefined_names, unknown_chars);
+        let plain = key(&Notation::default(), LintLevel::Allow, UnknownChars::Deny);
+        let notation = Notation {
+            words: vec![crate::lexer::notation::CustomWord { source: "grad".to_string(), tex: r"\nabla".to_string(), kind: DefKind::Function }],
+            ..Notation::default()
+        };
+        assert_ne!(plain, key(&notation, LintLevel::Allow, UnknownChars::Deny));
+        assert_ne!(plain, key(&Notation::default(), LintLevel::Warn, UnknownChars::Deny));
+        assert_ne!(plain, key(&Notation::default(), LintLevel::Allow, UnknownChars::Passthrough));
+    }
+
+    #[test]
+    fn lines_are_cached_by_text_and_context() {
+        let mut cache = LineCache::new();
+        let (plain, defined) = (context(&[]), context(&[("x", DefKind::Variable, None, 1)]));
+        cache.insert("x + 1", plain, "x+1".to_string());
+
+        assert_eq!(cache.get("x + 1", plain), Some("x+1"));
+        assert_eq!(cache.get("x + 2", plain), None);
+        assert_eq!(cache.get("x + 1", defined), None);
+        assert_eq!(cache.stats(), (1, 2));
+    }
+
+    #[test]
+    fn save_and_load() {
+        let path = cache_path("save_and_load");
+        let mut cache = LineCache::new();
+        let (plain, defined) = (context(&[]), context(&[("x", DefKind::Variable, None, 1)]));
+        // Text that spans lines and holds numbers, since entries are read by length
+        cache.insert("x + 1", plain, "x\n+\n1".to_string());
+        cache.insert("2 x", defined, "2 12 3\n".to_string());
+        cache.save(&path).unwrap();
+
+        let mut loaded = LineCache::load(&path).unwrap();
+        assert_eq!(loaded.get("x + 1", plain), Some("x\n+\n1"));
+        assert_eq!(loaded.get("2 x", defined), Some("2 12 3\n"));
+        assert_eq!(loaded.get("2 x", plain), None);
+
+        // A cache saved by a build that renders differently is ignored
+        let saved = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, saved.replacen("renderer", "renderer 0", 1)).unwrap();
+        let mut loaded = LineCache::load(&path).unwrap();
+        assert_eq!(loaded.get("x + 1", plain), None);
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(LineCache::load(&path).unwrap().entries.is_empty());
+    }
+
+    #[test]
+    fn directives_invalidate_later_lines() {
+        let mut cache = LineCache::new();
+        let document = "let x\nx + 1\n# Section\nx + 2\n";
+        assert_eq!(process(document, &mut cache), (0, 2));
+        assert_eq!(process(document, &mut cache), (2, 0));
+        // Editing a line only renders that line again
+        assert_eq!(process("let x\nx + 1\n# Section\nx + 3\n", &mut cache), (1, 1));
+        // Changing a definition renders every line after it again
+        assert_eq!(process("const x\nx + 1\n# Section\nx + 3\n", &mut cache), (0, 2));
+        assert_eq!(process("const x\nx + 1\n# Section\nfn y\nx + 3\n", &mut cache), (1, 1));
+        // As does new notation
+        assert_eq!(process("const x\nnotation \"grad\" as \\nabla function\nx + 1\n# Section\nfn y\nx + 3\n", &mut cache), (0, 2));
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+
+use crate::{to_tex::ToTex, lexer::span::Span};
+use super::DefKind;
+
+/// The set that the values of a definition are drawn from, as in `let x be in Real`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SetKind<'doc> {
+    /// Natural numbers `\N`
+    Natural,
+    /// Integers `\Z`
+    Integer,
+    /// Rational numbers `\Q`
+    Rational,
+    /// Real numbers `\R`
+    Real,
+    /// Complex numbers `\C`
+    Complex,
+    /// True or false `\B`
+    Boolean,
+    /// A set named by the user.
+    User(&'doc str),
+}
+
+impl<'doc> SetKind<'doc> {
+    /// Construct a set from the name used in a definition. Names that aren't builtin are user sets.
+    pub fn from_name(name: &'doc str) -> Self {
+        match name {
+            "Natural"  | "N" => Self::Natural,
+            "Integer"  | "Z" => Self::Integer,
+            "Rational" | "Q" => Self::Rational,
+            "Real"     | "R" => Self::Real,
+            "Complex"  | "C" => Self::Complex,
+            "Boolean"  | "B" => Self::Boolean,
+            _ => Self::User(name),
+        }
+    }
+}
+
+impl<'doc> ToTex for SetKind<'doc> {
+    fn to_tex(self) -> String {
+        match self {
+            SetKind::Natural  => r"\N".to_string(),
+            SetKind::Integer  => r"\Z".to_string(),
+            SetKind::Rational => r"\Q".to_string(),
+            SetKind::Real     => r"\R".to_string(),
+            SetKind::Complex  => r"\C".to_string(),
+            SetKind::Boolean  => r"\B".to_string(),
+            SetKind::User(name) => format!(r"\type{{{name}}}"),
+        }
+    }
+}
+
+/// Everything known about a name defined with `let`, `const`, or `fn`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Definition<'doc> {
+    /// What kind of object the name refers to.
+    pub kind: DefKind,
+    /// What set the object belongs to, if one was given.
+    pub set: Option<SetKind<'doc>>,
+    /// Where the name was defined.
+    pub span: Span,
+}
+
+/// Whether `name` refers to a set, either a builtin one like `Real` or one that definitions have been put in.
+pub fn is_set_name(definitions: &HashMap<&str, Definition<'_>>, name: &str) -> bool {
+    !matches!(SetKind::from_name(name), SetKind::User(_))
+        || definitions.values().any(|definition| definition.set == Some(SetKind::User(name)))
+}
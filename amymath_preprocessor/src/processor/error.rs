@@ -1,7 +1,11 @@
 use std::error::Error;
 
+use crate::diagnostic::{Diagnostic, ToDiagnostic};
 use crate::parser::error::ParseError;
-use crate::lexer::error::LexerError;
+use crate::lexer::{error::LexerError, span::Span};
+pub use super::semantics::{ValueType, error::TypeError};
+pub use super::lint::error::LintError;
+use super::DefKind;
 
 /// A preprocessor error that can be narrowed down to a particular line.
 #[derive(Debug)]
@@ -14,6 +18,83 @@ pub enum LineErrorKind {
     InvalidMetaItem,
     /// A heading was detected but malformed.
     InvalidHeading,
+    /// A definition was detected but malformed.
+    InvalidDefinition,
+    /// A subexpression has a type that doesn't fit where it is used.
+    TypeError(TypeError),
+    /// An optional check found a likely mistake.
+    LintError(LintError),
+    /// A name was defined again under the same heading as a different kind of object.
+    Redefinition {
+        /// The name being redefined.
+        name: String,
+        /// What the name is now.
+        kind: DefKind,
+        /// What the name was before.
+        previous_kind: DefKind,
+        /// Where the name was defined before.
+        previous_span: Span,
+    },
+    /// A builtin literal or function was defined by the user, hiding the builtin meaning.
+    ShadowsBuiltin {
+        /// The name being defined.
+        name: String,
+        /// What the name is now.
+        kind: DefKind,
+        /// What the builtin word is.
+        builtin_kind: DefKind,
+    },
+    /// `undef` was given a name that has no definition to remove.
+    UndefinedUndef {
+        /// The name that isn't defined.
+        name: String,
+    },
+    /// A `notation` directive was detected but could not be understood.
+    InvalidNotation(NotationError),
+    /// The file named by an `@import` directive could not be read.
+    ImportNotFound {
+        /// The path as written in the directive.
+        path: String,
+        /// Why the file could not be read.
+        error: String,
+    },
+    /// An `@import` directive names a file that is already being imported.
+    ImportCycle {
+        /// The names of the files in the cycle, starting and ending with the file imported twice.
+        cycle: Vec<String>,
+    },
+}
+
+/// What is wrong with a `notation` directive.
+#[derive(Debug)]
+pub enum NotationError {
+    /// The directive doesn't follow any of the `notation` forms.
+    Malformed,
+    /// The word after the TeX isn't a kind of operator or word.
+    UnknownKind(String),
+    /// The operator after `precedence of` doesn't exist.
+    UnknownOperator(String),
+    /// The source string is already an operator or bracket.
+    Taken(String),
+    /// A word was given a source string containing something other than letters.
+    NotAWord(String),
+}
+
+impl std::fmt::Display for NotationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotationError::Malformed
+                => write!(f, "expected `notation \"source\" as \\command` followed by what the notation is"),
+            NotationError::UnknownKind(kind)
+                => write!(f, "\"{kind}\" is not an operator kind (`operation`, `assertion`) or word kind (`literal`, `variable`, `constant`, `function`)"),
+            NotationError::UnknownOperator(op)
+                => write!(f, "\"{op}\" is not an operator"),
+            NotationError::Taken(token)
+                => write!(f, "\"{token}\" is already an operator or bracket"),
+            NotationError::NotAWord(token)
+                => write!(f, "\"{token}\" cannot be a word because it contains something other than letters"),
+        }
+    }
 }
 
 /// A [`LineErrorKind`] with line number.
@@ -21,6 +102,8 @@ pub enum LineErrorKind {
 pub struct LineError {
     /// The line the error occurred on.
     pub line_number: usize,
+    /// The region of the line the error occurred in.
+    pub span: Span,
     /// The specific error.
     pub kind: LineErrorKind,
 }
@@ -38,16 +121,96 @@ impl std::fmt::Display for LineError {
                     => "Meta items must start with '@' followed by the meta item key and then the value.".to_string(),
                 LineErrorKind::InvalidHeading
                     => "Headings must start with 1-4 '#'s followed by a space and then text.".to_string(),
+                LineErrorKind::InvalidDefinition
+                    => "Definitions must start with `let`, `const`, or `fn` (optionally preceded by `global`) followed by a list of names, and optionally `be in` a set.".to_string(),
+                LineErrorKind::TypeError(error)
+                    => error.to_string(),
+                LineErrorKind::LintError(error)
+                    => error.to_string(),
+                LineErrorKind::Redefinition { name, kind, previous_kind, previous_span }
+                    => format!("`{name}` was defined as a {previous_kind} at {previous_span} and is now redefined as a {kind}."),
+                LineErrorKind::ShadowsBuiltin { name, kind, builtin_kind }
+                    => format!("`{name}` is a builtin {builtin_kind} but is being defined as a {kind}."),
+                LineErrorKind::UndefinedUndef { name }
+                    => format!("`{name}` cannot be undefined because it has no definition."),
+                LineErrorKind::InvalidNotation(error)
+                    => format!("Invalid notation: {error}."),
+                LineErrorKind::ImportNotFound { path, error }
+                    => format!("Could not import \"{path}\": {error}."),
+                LineErrorKind::ImportCycle { cycle }
+                    => format!("Import cycle: {}.", cycle.join(" -> ")),
             }
         )
     }
 }
 
+impl ToDiagnostic for LineError {
+    fn to_diagnostic(&self) -> Diagnostic {
+        match &self.kind {
+            LineErrorKind::LexerError(error)
+                => error.to_diagnostic(),
+            LineErrorKind::ParseError(error)
+                => error.to_diagnostic(),
+            LineErrorKind::InvalidMetaItem
+                => Diagnostic::error("Malformed meta item")
+                    .with_primary(self.span, "expected a key and a value")
+                    .with_help("meta items are written as `@key value`"),
+            LineErrorKind::InvalidHeading
+                => Diagnostic::error("Malformed heading")
+                    .with_primary(self.span, "expected 1-4 '#'s, a space, and then text")
+                    .with_help("headings are written as `## Name`"),
+            LineErrorKind::InvalidDefinition
+                => Diagnostic::error("Malformed definition")
+                    .with_primary(self.span, "expected a list of names, optionally followed by the set they are in")
+                    .with_help("definitions are written as `let x, y be in Real`"),
+            LineErrorKind::TypeError(error)
+                => error.to_diagnostic(),
+            LineErrorKind::LintError(error)
+                => error.to_diagnostic(),
+            LineErrorKind::Redefinition { name, kind, previous_kind, previous_span }
+                => Diagnostic::warning(format!("`{name}` is redefined as a {kind}"))
+                    .with_primary(self.span, format!("redefined as a {kind} here"))
+                    .with_secondary(*previous_span, format!("previously defined as a {previous_kind} here"))
+                    .with_help("use a different name, or move one of the definitions under its own heading"),
+            LineErrorKind::ShadowsBuiltin { name, kind, builtin_kind }
+                => Diagnostic::warning(format!("`{name}` shadows a builtin {builtin_kind}"))
+                    .with_primary(self.span, format!("defined as a {kind} here"))
+                    .with_help(format!("use `undef {name}` to restore the builtin meaning")),
+            LineErrorKind::UndefinedUndef { name }
+                => Diagnostic::warning(format!("`{name}` is not defined"))
+                    .with_primary(self.span, "nothing to undefine")
+                    .with_help("only names defined with `let`, `const`, or `fn` can be undefined"),
+            LineErrorKind::InvalidNotation(error)
+                => Diagnostic::error("Invalid notation")
+                    .with_primary(self.span, error.to_string())
+                    .with_help(match error {
+                        NotationError::UnknownOperator(_)
+                            => r#"the precedence is given by an existing operator, like `precedence of +`"#,
+                        NotationError::Taken(_)
+                            => "notation cannot change the meaning of existing operators or brackets",
+                        _ => r#"notation is written as `notation "<~>" as \leftrightsquigarrow assertion precedence of =`, `notation "grad" as \nabla function`, or `notation "[|" "|]" as \llbracket \rrbracket`"#,
+                    }),
+            LineErrorKind::ImportNotFound { path, error }
+                => Diagnostic::error(format!("Could not import `{path}`"))
+                    .with_primary(self.span, error.clone())
+                    .with_help("import paths are relative to the file containing the `@import`"),
+            LineErrorKind::ImportCycle { cycle }
+                => cycle.windows(2).fold(
+                    Diagnostic::error("Import cycle")
+                        .with_primary(self.span, format!("`{}` is already being imported", cycle[0])),
+                    |diagnostic, pair| diagnostic.with_note(format!("`{}` imports `{}`", pair[0], pair[1])),
+                ).with_help("remove one of the imports in the cycle"),
+        }
+    }
+}
+
 impl Error for LineError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match &self.kind {
             LineErrorKind::LexerError(error) => Some(error),
             LineErrorKind::ParseError(error) => Some(error),
+            LineErrorKind::TypeError(error) => Some(error),
+            LineErrorKind::LintError(error) => Some(error),
             _ => None,
         }
     }
@@ -64,18 +227,28 @@ pub enum PreprocError {
 
 impl PreprocError {
     /// Shorthand for constructing a line error.
-    pub fn line_error(line_number: usize, kind: LineErrorKind) -> Self {
-        Self::LineError(LineError { line_number, kind })
+    pub fn line_error(span: Span, kind: LineErrorKind) -> Self {
+        Self::LineError(LineError { line_number: span.line, span, kind })
     }
 
     /// Shorthand for constructing a lexer line error.
-    pub fn lexer_error(line_number: usize, error: LexerError) -> Self {
-        Self::LineError(LineError { line_number, kind: LineErrorKind::LexerError(error) })
+    pub fn lexer_error(error: LexerError) -> Self {
+        Self::line_error(error.span(), LineErrorKind::LexerError(error))
     }
 
     /// Shorthand for constructing a parser line error.
-    pub fn parse_error(line_number: usize, error: ParseError) -> Self {
-        Self::LineError(LineError { line_number, kind: LineErrorKind::ParseError(error) })
+    pub fn parse_error(error: ParseError) -> Self {
+        Self::line_error(error.span(), LineErrorKind::ParseError(error))
+    }
+
+    /// Shorthand for constructing a type checking line error.
+    pub fn type_error(error: TypeError) -> Self {
+        Self::line_error(error.span(), LineErrorKind::TypeError(error))
+    }
+
+    /// Shorthand for constructing a lint line error.
+    pub fn lint_error(error: LintError) -> Self {
+        Self::line_error(error.span(), LineErrorKind::LintError(error))
     }
 }
 
@@ -92,6 +265,18 @@ impl std::fmt::Display for PreprocError {
     }
 }
 
+impl ToDiagnostic for PreprocError {
+    fn to_diagnostic(&self) -> Diagnostic {
+        match self {
+            PreprocError::LineError(error)
+                => error.to_diagnostic(),
+            PreprocError::TemplateMissingContent
+                => Diagnostic::error("Template is missing a content anchor")
+                    .with_help(format!("add `{}` to the template where the content should be inserted", super::CONTENT_ANCHOR)),
+        }
+    }
+}
+
 impl Error for PreprocError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
@@ -100,3 +285,74 @@ impl Error for PreprocError {
         }
     }
 }
+
+/// Every error and warning found while preprocessing a document.
+#[derive(Debug, Default)]
+pub struct Report {
+    /// The errors, in the order they were found.
+    pub errors: Vec<PreprocError>,
+    /// Problems that don't stop the document from being processed, in the order they were found.
+    pub warnings: Vec<PreprocError>,
+    /// Whether processing was stopped early because [`ProcessOptions::error_limit`](super::ProcessOptions::error_limit) was reached.
+    pub limit_reached: bool,
+    /// The number of errors to stop at.
+    limit: Option<usize>,
+}
+
+impl Report {
+    /// Construct an empty report.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Construct an empty report that is marked as [`Report::limit_reached`] once it holds `limit` errors.
+    pub fn with_limit(limit: Option<usize>) -> Self {
+        Self { limit, ..Self::default() }
+    }
+
+    /// Add an error to the report.
+    pub fn push(&mut self, error: PreprocError) {
+        self.errors.push(error);
+        if self.limit.is_some_and(|limit| self.errors.len() >= limit) {
+            self.limit_reached = true;
+        }
+    }
+
+    /// Add a warning to the report.
+    pub fn warn(&mut self, warning: PreprocError) {
+        self.warnings.push(warning);
+    }
+
+    /// A one-line description of how many errors were found.
+    pub fn summary(&self) -> String {
+        let count = self.errors.len();
+        let plural = if count == 1 { "" } else { "s" };
+        if self.limit_reached {
+            format!("stopped after {count} error{plural}; the error limit was reached")
+        } else {
+            format!("could not process the document due to {count} error{plural}")
+        }
+    }
+
+    /// A one-line description of how many warnings were found, if there were any.
+    pub fn warning_summary(&self) -> Option<String> {
+        let count = self.warnings.len();
+        let plural = if count == 1 { "" } else { "s" };
+        (count > 0).then(|| format!("generated {count} warning{plural}"))
+    }
+}
+
+impl std::fmt::Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for error in &self.errors {
+            writeln!(f, "{error}")?;
+        }
+        write!(f, "{}", self.summary())
+    }
+}
+
+impl Error for Report {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.errors.first().map(|error| error as &(dyn Error + 'static))
+    }
+}
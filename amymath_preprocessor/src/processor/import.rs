@@ -0,0 +1,237 @@
+use std::{collections::HashSet, io, path::{Path, PathBuf}};
+
+use crate::{lexer::span::{FileId, Span}, source_map::SourceMap};
+use super::error::*;
+
+/// Where the files named by `@import` directives are read from.
+pub trait Files {
+    /// The path that identifies the file at `path`, so that a file reached by different paths is only loaded once.
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+    /// The content of the file at a canonical path.
+    fn read(&self, path: &Path) -> io::Result<String>;
+}
+
+/// The files on disk.
+pub struct Disk;
+
+impl Files for Disk {
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        path.canonicalize()
+    }
+
+    fn read(&self, path: &Path) -> io::Result<String> {
+        std::fs::read_to_string(path)
+    }
+}
+
+/// The lines of a file with comments removed, numbered from 1, skipping lines that are blank.
+pub fn code_lines(content: &str) -> impl Iterator<Item = (usize, &str)> {
+    content
+        .lines()
+        .enumerate()
+        // Remove comments
+        .map(|(n, line)|
+            (n+1, if let Some(comment_start) = line.find("%") {
+                &line[..comment_start]
+            } else {
+                line
+            })
+        )
+        // Remove blank lines
+        .filter(|(_n, code)| !code.trim().is_empty())
+}
+
+/// If the line is an `@import` directive, get the path it imports and the byte offset of the path in the line.
+fn import_path(code: &str) -> Option<(&str, usize)> {
+    let line = code.trim_start();
+    let path = line.strip_prefix("@import")?;
+    if !path.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let path = path.trim_start();
+    Some((path.trim_end(), code.len() - path.len()))
+}
+
+/// Load every file imported by `file` from `files` into `sources`, and every file those import in turn.
+///
+/// `chain` holds the files currently being imported, outermost first, so that cycles can be reported instead of followed.
+/// Imports that can't be loaded are reported and left unresolved.
+pub fn load_imports(files: &impl Files, sources: &mut SourceMap, file: FileId, chain: &mut Vec<FileId>, report: &mut Report) {
+    chain.push(file);
+
+    let imports: Vec<(usize, String, Span)> = code_lines(&sources.file(file).content)
+        .filter_map(|(line_number, code)| {
+            let (path, start) = import_path(code)?;
+            Some((line_number, path.to_string(), Span::new(file, line_number, start, start + path.len())))
+        })
+        .collect();
+
+    for (line_number, path_str, span) in imports {
+        let path = sources.file(file).path
+            .parent()
+            .map_or_else(|| path_str.clone().into(), |dir| dir.join(&path_str));
+
+        let loaded = files.canonicalize(&path).and_then(|path| match sources.find(&path) {
+            Some(id) => Ok(Ok(id)),
+            None => files.read(&path).map(|content| Err((path, content))),
+        });
+
+        let target = match loaded {
+            Err(error) => {
+                report.push(PreprocError::line_error(span, LineErrorKind::ImportNotFound { path: path_str, error: error.to_string() }));
+                continue;
+            },
+            Ok(Ok(id)) if chain.contains(&id) => {
+                let cycle_start = chain.iter().position(|file| *file == id).unwrap();
+                let cycle = chain[cycle_start..].iter()
+                    .chain([&id])
+                    .map(|file| sources.file(*file).name.clone())
+                    .collect();
+                report.push(PreprocError::line_error(span, LineErrorKind::ImportCycle { cycle }));
+                continue;
+            },
+            // Already loaded along with its own imports
+            Ok(Ok(id)) => id,
+            Ok(Err((path, content))) => {
+                let id = sources.add(path, content, span);
+                load_imports(files, sources, id, chain, report);
+                id
+            },
+        };
+        sources.resolve_import(file, line_number, target);
+    }
+
+    chain.pop();
+}
+
+/// The lines of `file` with the lines of imported files spliced in where they are imported.
+///
+/// Each line is given with the file it is from and its line number in that file.
+/// A file that is imported more than once, like one shared by two other imports, is only spliced in where it is first imported,
+/// so that its definitions and notation aren't made again.
+pub fn expand_lines<'doc>(sources: &'doc SourceMap, file: FileId, lines: &mut Vec<(FileId, usize, &'doc str)>) {
+    expand_file(sources, file, &mut HashSet::from([file]), lines);
+}
+
+/// Splice in the lines of `file`, skipping imports of anything in `spliced`.
+fn expand_file<'doc>(sources: &'doc SourceMap, file: FileId, spliced: &mut HashSet<FileId>, lines: &mut Vec<(FileId, usize, &'doc str)>) {
+    for (line_number, code) in code_lines(&sources.file(file).content) {
+        if import_path(code).is_some() {
+            // Unresolved imports have already been reported
+            if let Some(target) = sources.import(file, line_number) {
+                if spliced.insert(target) {
+                    expand_file(sources, target, spliced, lines);
+                }
+            }
+        } else {
+            lines.push((file, line_number, code));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// Files held in memory, by path.
+    struct Memory(HashMap<PathBuf, String>);
+
+    impl Files for Memory {
+        fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+            match self.0.contains_key(path) {
+                true => Ok(path.to_path_buf()),
+                false => Err(io::ErrorKind::NotFound.into()),
+            }
+        }
+
+        fn read(&self, path: &Path) -> io::Result<String> {
+            self.0.get(path).cloned().ok_or(io::ErrorKind::NotFound.into())
+        }
+    }
+
+    /// Load the imports of `/doc/main.math` from the other files, which are in `/doc` too.
+    fn load(main: &str, files: &[(&str, &str)]) -> (SourceMap, Report) {
+        let files = Memory(files.iter()
+            .map(|(name, content)| (Path::new("/doc").join(name), content.to_string()))
+            .collect());
+        let mut sources = SourceMap::new(PathBuf::from("/doc/main.math"), main.to_string());
+        let mut report = Report::new();
+        load_imports(&files, &mut sources, 0, &mut Vec::new(), &mut report);
+        (sources, report)
+    }
+
+    /// Each line of the expanded document, as its text.
+    fn expanded(sources: &SourceMap) -> Vec<&str> {
+        let mut lines = Vec::new();
+        expand_lines(sources, 0, &mut lines);
+        lines.into_iter().map(|(_, _, code)| code).collect()
+    }
+
+    /// The kind of each error in the report.
+    fn error_kinds(report: Report) -> Vec<LineErrorKind> {
+        report.errors.into_iter()
+            .map(|error| match error {
+                PreprocError::LineError(error) => error.kind,
+                error => panic!("{error:?} should be a line error"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn splices_imports_in_place() {
+        let (sources, report) = load("a\n@import defs.math\nb", &[("defs.math", "let x % defined here\n\nc")]);
+        assert!(report.errors.is_empty());
+        assert_eq!(expanded(&sources), ["a", "let x ", "c", "b"]);
+    }
+
+    #[test]
+    fn splices_each_file_once() {
+        let (sources, report) = load("@import left.math\n@import right.math\n@import left.math", &[
+            ("left.math", "left\n@import shared.math"),
+            ("right.math", "right\n@import shared.math"),
+            ("shared.math", "shared"),
+        ]);
+        assert!(report.errors.is_empty());
+        assert_eq!(sources.files().len(), 4);
+        assert_eq!(expanded(&sources), ["left", "shared", "right"]);
+    }
+
+    #[test]
+    fn reports_cycles() {
+        let (sources, report) = load("@import a.math", &[
+            ("a.math", "a\n@import b.math"),
+            ("b.math", "b\n@import a.math"),
+        ]);
+        let kinds = error_kinds(report);
+        assert!(matches!(&kinds[..], [LineErrorKind::ImportCycle { cycle }] if cycle == &["/doc/a.math", "/doc/b.math", "/doc/a.math"]), "{kinds:?}");
+        // The import that closes the cycle is left out
+        assert_eq!(expanded(&sources), ["a", "b"]);
+
+        let (_, report) = load("@import main.math", &[("main.math", "")]);
+        assert_eq!(report.errors.len(), 1);
+    }
+
+    #[test]
+    fn reports_missing_files() {
+        let (sources, report) = load("a\n@import missing.math\nb", &[]);
+        let kinds = error_kinds(report);
+        assert!(matches!(&kinds[..], [LineErrorKind::ImportNotFound { path, .. }] if path == "missing.math"), "{kinds:?}");
+        assert_eq!(expanded(&sources), ["a", "b"]);
+    }
+
+    #[test]
+    fn import_chain() {
+        let (sources, _) = load("\n@import outer.math", &[
+            ("outer.math", "@import inner.math"),
+            ("inner.math", "x"),
+        ]);
+        let inner = sources.find(Path::new("/doc/inner.math")).unwrap();
+        let chain: Vec<_> = sources.import_chain(inner).into_iter()
+            .map(|span| (sources.file(span.file).name.as_str(), span.line, span.start))
+            .collect();
+        assert_eq!(chain, [("/doc/outer.math", 1, 8), ("/doc/main.math", 2, 8)]);
+        assert!(sources.import_chain(0).is_empty());
+    }
+}
@@ -0,0 +1,54 @@
+use std::error::Error;
+
+use crate::diagnostic::{Diagnostic, Severity, ToDiagnostic};
+use crate::lexer::span::Span;
+
+/// Something in the document that is allowed, but probably a mistake.
+#[derive(Debug)]
+pub enum LintError {
+    /// A name is used without having been defined with `let`, `const`, or `fn`.
+    UndefinedName {
+        /// The name as written in the source.
+        name: String,
+        /// The closest defined or builtin name, if any are close enough to be a typo.
+        suggestion: Option<String>,
+        /// Where the name is used.
+        span: Span,
+        /// Whether the lint is reported as a warning or an error.
+        severity: Severity,
+    },
+}
+
+impl LintError {
+    /// The region of the source the lint is reported at.
+    pub fn span(&self) -> Span {
+        match self {
+            LintError::UndefinedName { span, .. } => *span,
+        }
+    }
+}
+
+impl std::fmt::Display for LintError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LintError::UndefinedName { name, span, .. }
+                => write!(f, "`{name}` is used at {span} but was never defined."),
+        }
+    }
+}
+
+impl ToDiagnostic for LintError {
+    fn to_diagnostic(&self) -> Diagnostic {
+        match self {
+            LintError::UndefinedName { name, suggestion, span, severity }
+                => Diagnostic::new(*severity, format!("Undefined name `{name}`"))
+                    .with_primary(*span, "not defined")
+                    .with_help(match suggestion {
+                        Some(suggestion) => format!("a name with a similar spelling exists: `{suggestion}`"),
+                        None => format!("define it before it is used, like `let {name}`"),
+                    }),
+        }
+    }
+}
+
+impl Error for LintError {}
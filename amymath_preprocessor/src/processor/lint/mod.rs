@@ -0,0 +1,188 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::diagnostic::Severity;
+use crate::lexer::{*, builtin::BuiltinWordToken};
+use crate::parser::syntax_tree::{SyntaxNode, SyntaxTree};
+use super::definition::{Definition, is_set_name};
+
+/// `lint` error module.
+pub mod error;
+use error::*;
+
+/// How an optional check reports what it finds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+pub enum LintLevel {
+    /// Don't check.
+    #[default]
+    Allow,
+    /// Report findings as warnings.
+    Warn,
+    /// Report findings as errors, stopping the document from being processed.
+    Deny,
+}
+
+impl LintLevel {
+    /// The severity findings are reported with. `None` if the check shouldn't run.
+    pub fn severity(&self) -> Option<Severity> {
+        match self {
+            LintLevel::Allow => None,
+            LintLevel::Warn => Some(Severity::Warning),
+            LintLevel::Deny => Some(Severity::Error),
+        }
+    }
+}
+
+/// The number of single-character insertions, deletions, or substitutions it takes to turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_ch) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b_ch) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a_ch != *b_ch);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// The word a node begins with, like `i` in `i = 0, n`.
+fn leftmost_name<'doc>(node: &SyntaxNode<'doc>) -> Option<&'doc str> {
+    match node {
+        SyntaxNode::Token(Token { kind: TokenKind::Word(WordToken::Direct(dw_token)), .. }) => Some(dw_token.name),
+        SyntaxNode::Operator { lhs, .. } => lhs.first().and_then(leftmost_name),
+        _ => None,
+    }
+}
+
+/// Collect the names introduced by the expression itself rather than by a definition,
+/// like `i` in `sum[i = 0, n]` and `x` in `for all x in S`.
+fn bound_names<'doc>(node: &SyntaxNode<'doc>, bound: &mut HashSet<&'doc str>) {
+    let bind_tree = |tree: &SyntaxTree<'doc>, bound: &mut HashSet<&'doc str>| {
+        for node in &tree.nodes {
+            bound_names(node, bound);
+        }
+    };
+
+    match node {
+        SyntaxNode::Token(_) => (),
+
+        SyntaxNode::Operator { lhs, op, rhs, .. } => {
+            let is_quantifier = matches!(op,
+                | OperatorToken::Forall
+                | OperatorToken::Exists
+                | OperatorToken::NExists
+                | OperatorToken::ExistsUnique
+                | OperatorToken::NExistsUnique
+            );
+            if let (true, Some(name)) = (is_quantifier, rhs.first().and_then(leftmost_name)) {
+                bound.insert(name);
+            }
+            for node in lhs.iter().chain(rhs) {
+                bound_names(node, bound);
+            }
+        },
+
+        SyntaxNode::Apply { subscript, superscript, args, .. } => {
+            for tree in [subscript.as_ref(), superscript.as_ref(), Some(args)].into_iter().flatten() {
+                bind_tree(tree, bound);
+            }
+        },
+
+        SyntaxNode::BigOp { op, index, body, .. } => {
+            if let Some(index) = index {
+                if let (BigOpForm::Bounds, Some(name)) = (op.form(), index.nodes.first().and_then(leftmost_name)) {
+                    bound.insert(name);
+                }
+                bind_tree(index, bound);
+            }
+            bind_tree(body, bound);
+        },
+
+        SyntaxNode::Group { inner, .. } => bind_tree(inner, bound),
+    }
+}
+
+/// Finds the names in a syntax tree that were never defined.
+pub struct UndefinedNames<'def, 'doc> {
+    definitions: &'def HashMap<&'doc str, Definition<'doc>>,
+    bound: HashSet<&'doc str>,
+    severity: Severity,
+    errors: Vec<LintError>,
+}
+
+impl<'def, 'doc> UndefinedNames<'def, 'doc> {
+    /// Construct a lint that looks names up in `definitions`, reporting with `severity`.
+    pub fn new(definitions: &'def HashMap<&'doc str, Definition<'doc>>, severity: Severity) -> Self {
+        Self { definitions, bound: HashSet::new(), severity, errors: Vec::new() }
+    }
+
+    /// Report every undefined name in the tree, in the order they are written.
+    pub fn check(mut self, tree: &SyntaxTree<'doc>) -> Vec<LintError> {
+        for node in &tree.nodes {
+            bound_names(node, &mut self.bound);
+        }
+        self.check_tree(tree);
+        self.errors
+    }
+
+    /// The closest defined or builtin name to `name`, if it is close enough to be a typo.
+    fn suggest(&self, name: &str) -> Option<String> {
+        let len = name.chars().count();
+        let max_distance = (len / 3).max(1);
+        self.definitions.keys()
+            .chain(BuiltinWordToken::names())
+            .map(|candidate| (edit_distance(name, candidate), *candidate))
+            .filter(|(distance, _)| *distance <= max_distance && *distance < len)
+            .min()
+            .map(|(_, candidate)| candidate.to_string())
+    }
+
+    fn check_tree(&mut self, tree: &SyntaxTree<'doc>) {
+        for node in &tree.nodes {
+            self.check_node(node);
+        }
+    }
+
+    fn check_node(&mut self, node: &SyntaxNode<'doc>) {
+        match node {
+            SyntaxNode::Token(Token { kind: TokenKind::Word(WordToken::Direct(dw_token)), span }) => {
+                let name = dw_token.name;
+                // Words with their own command were added with `notation`
+                if dw_token.command.is_none() && !self.definitions.contains_key(name) && !self.bound.contains(name) && !is_set_name(self.definitions, name) {
+                    self.errors.push(LintError::UndefinedName {
+                        name: name.to_string(),
+                        suggestion: self.suggest(name),
+                        span: *span,
+                        severity: self.severity,
+                    });
+                }
+            },
+
+            SyntaxNode::Token(_) => (),
+
+            SyntaxNode::Operator { lhs, rhs, .. } => {
+                for node in lhs.iter().chain(rhs) {
+                    self.check_node(node);
+                }
+            },
+
+            SyntaxNode::Apply { subscript, superscript, args, .. } => {
+                for tree in [subscript.as_ref(), superscript.as_ref(), Some(args)].into_iter().flatten() {
+                    self.check_tree(tree);
+                }
+            },
+
+            SyntaxNode::BigOp { index, body, .. } => {
+                if let Some(index) = index {
+                    self.check_tree(index);
+                }
+                self.check_tree(body);
+            },
+
+            SyntaxNode::Group { inner, .. } => self.check_tree(inner),
+        }
+    }
+}
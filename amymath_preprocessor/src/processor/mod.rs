@@ -1,14 +1,34 @@
-use std::collections::{BTreeMap, HashMap};
-use direct::DirectWordToken;
+use std::collections::BTreeMap;
+use builtin::BuiltinWordToken;
 use regex::Regex;
-use crate::{to_tex::ToTex, lexer::*, parser::parse};
+use crate::{to_tex::ToTex, lexer::*, parser::parse, source_map::SourceMap, log::{log, Verbosity}};
 
 /// `processor` error module.
 pub mod error;
+/// Information attached to defined names.
+pub(crate) mod definition;
+/// Type checking of parsed expressions.
+pub(crate) mod semantics;
+/// Optional checks for likely mistakes.
+pub(crate) mod lint;
+/// Definitions that follow the heading hierarchy.
+pub(crate) mod scope;
+/// Operators, words, and brackets added with the `notation` directive.
+pub(crate) mod notation;
+/// Splicing in other files with the `@import` directive.
+pub(crate) mod import;
+/// Reusing the TeX of lines that haven't changed.
+pub(crate) mod cache;
 use error::*;
+use definition::*;
+use semantics::TypeChecker;
+use lint::{LintLevel, UndefinedNames};
+use scope::Scopes;
+use notation::NotationItem;
+use cache::{ContextKey, LineCache};
 
 /// What type of definition this is.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum DefKind {
     /// A special number (or mathematical constant) that is baked into the formula.
     Literal,
@@ -31,6 +51,17 @@ impl ToTex for DefKind {
     }
 }
 
+impl std::fmt::Display for DefKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DefKind::Literal  => write!(f, "literal"),
+            DefKind::Variable => write!(f, "variable"),
+            DefKind::Constant => write!(f, "constant"),
+            DefKind::Function => write!(f, "function"),
+        }
+    }
+}
+
 /// The title of a region in the document.
 #[derive(Debug, Clone)]
 struct Heading<'doc> {
@@ -57,50 +88,160 @@ enum ContentItem<'doc> {
 
 const CONTENT_ANCHOR: &str = "@{content}";
 
+/// The template used when none is given: a LaTeX document defining the commands the processed math uses.
+pub const DEFAULT_TEMPLATE: &str = include_str!("../template.sty");
+
 /// Generates the anchor string for a given meta key.
 fn anchor_str(key: &str) -> String {
     format!("@{{{key}}}")
 }
 
+/// Settings that change how a document is processed.
+#[derive(Debug, Clone, Default)]
+pub struct ProcessOptions {
+    /// Stop processing once this many errors have been found. `None` for no limit.
+    pub error_limit: Option<usize>,
+    /// Render definitions that name a set, like `let x, y be in Real`, as a line of math (`x, y \in \R`).
+    pub emit_declarations: bool,
+    /// How to report names that are used without being defined.
+    pub undefined_names: LintLevel,
+    /// What to do with characters that aren't part of the language.
+    pub unknown_chars: UnknownChars,
+}
+
+/// Preprocesses documents into LaTeX.
+///
+/// Configured by chaining settings onto [`Processor::new`], then reused for any number of documents.
+#[derive(Debug, Clone)]
+pub struct Processor {
+    template: String,
+    options: ProcessOptions,
+}
+
+impl Default for Processor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Processor {
+    /// Constructs a processor with the [default template](DEFAULT_TEMPLATE) and default options.
+    pub fn new() -> Self {
+        Self {
+            template: DEFAULT_TEMPLATE.to_string(),
+            options: ProcessOptions::default(),
+        }
+    }
+
+    /// Use `template` for the output. The processed document replaces `@{content}`, and meta items replace `@{key}`.
+    pub fn template(mut self, template: impl Into<String>) -> Self {
+        self.template = template.into();
+        self
+    }
+
+    /// Replace every option at once.
+    pub fn options(mut self, options: ProcessOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Stop processing once this many errors have been found. `None` for no limit.
+    pub fn error_limit(mut self, limit: Option<usize>) -> Self {
+        self.options.error_limit = limit;
+        self
+    }
+
+    /// Render definitions that name a set as a line of math.
+    pub fn emit_declarations(mut self, emit: bool) -> Self {
+        self.options.emit_declarations = emit;
+        self
+    }
+
+    /// How to report names that are used without being defined.
+    pub fn undefined_names(mut self, level: LintLevel) -> Self {
+        self.options.undefined_names = level;
+        self
+    }
+
+    /// What to do with characters that aren't part of the language.
+    pub fn unknown_chars(mut self, unknown_chars: UnknownChars) -> Self {
+        self.options.unknown_chars = unknown_chars;
+        self
+    }
+
+    /// Process the document that is the first file in `sources`.
+    ///
+    /// Files it imports are loaded into `sources`, so that diagnostics in the report can be rendered with it afterward.
+    /// On success, returns the output along with a report holding any warnings.
+    pub fn process(&self, sources: &mut SourceMap) -> Result<(String, Report), Report> {
+        process_document(sources, &self.template, &self.options, None)
+    }
+
+    /// Like [`Processor::process`], but lines of math whose TeX is in `cache` are not rendered again,
+    /// and newly rendered lines are added to it.
+    ///
+    /// Keeping one cache across runs makes processing an edited document take time in proportion to the edit.
+    pub fn process_cached(&self, sources: &mut SourceMap, cache: &mut LineCache) -> Result<(String, Report), Report> {
+        process_document(sources, &self.template, &self.options, Some(cache))
+    }
+}
+
 /// Apply preprocessing to the document.
-pub fn process_document<'doc>(document: &'doc str, template: &str) -> Result<String, PreprocError> {
-    let rx_def = Regex::new(r"^(?<kind>fn|let|const)\s+(?<names>(?:[a-zA-Z]+)(?:,\s*[a-zA-Z]+)*)\b").unwrap();
+///
+/// The document is the first file in `sources`. Files it imports with `@import` are loaded into `sources` and spliced in.
+/// Lines with errors are skipped so that every error in the document can be reported at once.
+/// On success, the report holds any warnings found along the way.
+fn process_document(sources: &mut SourceMap, template: &str, options: &ProcessOptions, mut cache: Option<&mut LineCache>) -> Result<(String, Report), Report> {
+    // Names are lexed as words, so this matches what `Lexer::tokenize` reads as a single word
+    let name = r"[a-zA-Z]+(?:[0-9]+|_[a-zA-Z]{2,})?";
+    let rx_def = Regex::new(&format!(r"^(?:(?<global>global)\s+)?(?<kind>fn|let|const)\s+(?<names>{name}(?:,\s*{name})*)\b(?:\s+(?:be|is|are)\s+in\s+(?<set>[a-zA-Z]+)\b)?")).unwrap();
+    let rx_def_start = Regex::new(r"^(?:global\s+)?(?:fn|let|const)\b").unwrap();
+    let rx_undef = Regex::new(&format!(r"^undef\s+(?<names>{name}(?:,\s*{name})*)$")).unwrap();
+    let rx_notation = Regex::new(notation::PATTERN).unwrap();
+    let rx_name = Regex::new(name).unwrap();
+
+    let mut report = Report::with_limit(options.error_limit);
 
     if !template.contains(CONTENT_ANCHOR) {
-        return Err(PreprocError::TemplateMissingContent)
-    }
-
-    let lines = document
-        .lines()
-        .enumerate()
-        // Remove comments
-        .map(|(n, line)|
-            (n+1, if let Some(comment_start) = line.find("%") {
-                &line[..comment_start]
-            } else {
-                line
-            }.trim())
-        )
-        // Remove blank lines
-        .filter(|(_n, line)| !line.is_empty());
+        report.push(PreprocError::TemplateMissingContent);
+        return Err(report);
+    }
+
+    import::load_imports(&import::Disk, sources, 0, &mut Vec::new(), &mut report);
+    let sources = &*sources;
+    let mut lines = Vec::new();
+    import::expand_lines(sources, 0, &mut lines);
 
     let mut meta = BTreeMap::<&str, &str>::from([
         ("author", "Unknown"),
         ("title", "Unnamed"),
     ]);
-    let mut definitions = HashMap::<&'doc str, DefKind>::new();
+    let mut scopes = Scopes::new();
     let mut content = Vec::<ContentItem>::new();
 
-    let lexer = Lexer::new();
-    for (line_number, line) in lines {
+    let mut lexer = Lexer::new().unknown_chars(options.unknown_chars);
+    // Reset by every directive that changes definitions or notation
+    let mut context = None;
+    for (file, line_number, code) in lines {
+        if report.limit_reached {
+            break;
+        }
+
+        // Untrimmed `code` is kept so that token spans match columns in the source
+        let line = code.trim();
+        let line_span = Span::new(file, line_number, code.len() - code.trim_start().len(), code.trim_end().len());
+
         // Meta item
-        if line.starts_with("@") {
-            match line[1..].split_once(" ") {
+        if let Some(meta_item) = line.strip_prefix("@") {
+            match meta_item.split_once(" ") {
                 Some((key, value)) => {
-                    println!("Meta item: \"{key}\"=\"{value}\"");
+                    log!(Verbosity::Progress, "Meta item: \"{key}\"=\"{value}\"");
                     meta.insert(key, value);
                 },
-                None => return Err(PreprocError::line_error(line_number, LineErrorKind::InvalidMetaItem)),
+                None => {
+                    report.push(PreprocError::line_error(line_span, LineErrorKind::InvalidMetaItem));
+                    continue;
+                },
             };
         }
         // Heading item
@@ -110,18 +251,61 @@ pub fn process_document<'doc>(document: &'doc str, template: &str) -> Result<Str
                     assert!(!depth.is_empty(), "Heading should not be created with 0 '#' symbols");
                     assert!(!name.is_empty(), "Heading should not be created without text");
                     let depth = depth.len();
-                    println!("Heading: \"{name}\" Depth: {depth}");
+                    log!(Verbosity::Progress, "Heading: \"{name}\" Depth: {depth}");
                     Heading{ depth, name }
                 },
-                _ => return Err(PreprocError::line_error(line_number, LineErrorKind::InvalidHeading)),
+                _ => {
+                    report.push(PreprocError::line_error(line_span, LineErrorKind::InvalidHeading));
+                    continue;
+                },
             };
+            scopes.enter_heading(heading.depth);
+            context = None;
             content.push(ContentItem::Heading(heading));
         }
+        // New operators, words, and brackets
+        else if line.split_whitespace().next() == Some("notation") {
+            let item = rx_notation.captures(line)
+                .ok_or(NotationError::Malformed)
+                .and_then(|caps| NotationItem::from_captures(&caps, lexer.notation()));
+            match item {
+                Ok(item) => {
+                    log!(Verbosity::Progress, "Notation: {item:?}");
+                    item.add_to(&mut lexer);
+                    context = None;
+                },
+                Err(error) => {
+                    report.push(PreprocError::line_error(line_span, LineErrorKind::InvalidNotation(error)));
+                    continue;
+                },
+            }
+        }
+        // Removal of definitions
+        else if let Some(caps) = rx_undef.captures(line) {
+            context = None;
+            let names_match = caps.name("names").unwrap();
+            for name in rx_name.find_iter(names_match.as_str()) {
+                log!(Verbosity::Progress, "Undefining \"{}\"", name.as_str());
+                if scopes.undefine(name.as_str()).is_none() {
+                    let start = line_span.start + names_match.start() + name.start();
+                    let span = Span::new(file, line_number, start, start + name.len());
+                    report.warn(PreprocError::line_error(span, LineErrorKind::UndefinedUndef { name: name.as_str().to_string() }));
+                }
+            }
+        }
         // Object definition
         else if let Some(caps) = rx_def.captures(line) {
             let kind_str = caps.name("kind").unwrap().as_str();
-            let names_str = caps.name("names").unwrap().as_str();
+            let names_match = caps.name("names").unwrap();
+            let is_global = caps.name("global").is_some();
+            let set = caps.name("set").map(|set| SetKind::from_name(set.as_str()));
+
+            if caps.get(0).unwrap().end() != line.len() {
+                report.push(PreprocError::line_error(line_span, LineErrorKind::InvalidDefinition));
+                continue;
+            }
 
+            context = None;
             let kind = match kind_str {
                 "let"   => DefKind::Variable,
                 "const" => DefKind::Constant,
@@ -130,39 +314,138 @@ pub fn process_document<'doc>(document: &'doc str, template: &str) -> Result<Str
             };
 
             // The set of items that are all being defined on the same line
-            let names = names_str.split(",").map(str::trim);
+            let names: Vec<(&str, Span)> = rx_name.find_iter(names_match.as_str())
+                .map(|name| {
+                    let start = line_span.start + names_match.start() + name.start();
+                    (name.as_str(), Span::new(file, line_number, start, start + name.len()))
+                })
+                .collect();
 
-            for name in names {
-                println!("Defining \"{name}\" as {kind:?}");
-                definitions.insert(name, kind);
+            for &(name, span) in &names {
+                log!(Verbosity::Progress, "Defining \"{name}\" as {kind:?} in {set:?}");
+                let previous = scopes.define(name, Definition { kind, set, span }, is_global);
+                if let Some(previous) = previous.filter(|previous| previous.kind != kind) {
+                    report.warn(PreprocError::line_error(span, LineErrorKind::Redefinition {
+                        name: name.to_string(),
+                        kind,
+                        previous_kind: previous.kind,
+                        previous_span: previous.span,
+                    }));
+                }
+                if let Some(bw_token) = BuiltinWordToken::try_from(name) {
+                    // Builtin variables like `phi` are free to define without a warning
+                    if matches!(bw_token.kind(), DefKind::Literal | DefKind::Function) {
+                        report.warn(PreprocError::line_error(span, LineErrorKind::ShadowsBuiltin {
+                            name: name.to_string(),
+                            kind,
+                            builtin_kind: bw_token.kind(),
+                        }));
+                    }
+                }
             }
+
+            if let (true, Some(set)) = (options.emit_declarations, set) {
+                let names_tex = names.into_iter()
+                    .map(|(name, _)| WordToken::defined(name, kind).to_tex())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let tex = format!("{names_tex} {}{{{}}} {}",
+                    OpType::Assertion.to_tex(),
+                    OperatorToken::In.to_tex(),
+                    set.to_tex(),
+                );
+                match content.last_mut() {
+                    Some(ContentItem::Math(math)) => math.push(tex),
+                    _ => content.push(ContentItem::Math(vec![tex])),
+                }
+            }
+        }
+        // Lines that start like a definition but aren't one, like `let x_i be in Real`
+        else if rx_def_start.is_match(line) {
+            report.push(PreprocError::line_error(line_span, LineErrorKind::InvalidDefinition));
+            continue;
         }
         // Math
         else {
-            println!("line: {line}");
+            log!(Verbosity::Tokens, "line: {line}");
+
+            let definitions = scopes.visible();
+
+            let context = *context.get_or_insert_with(|| {
+                ContextKey::new(definitions, lexer.notation(), options.undefined_names, options.unknown_chars)
+            });
+            if let Some(tex) = cache.as_deref_mut().and_then(|cache| cache.get(line, context)) {
+                log!(Verbosity::Tokens, "cached: {tex}");
+                let tex = tex.to_string();
+                match content.last_mut() {
+                    Some(ContentItem::Math(math)) => math.push(tex),
+                    _ => content.push(ContentItem::Math(vec![tex])),
+                }
+                continue;
+            }
+            let diagnostic_count = (report.errors.len(), report.warnings.len());
 
-            let tokens: Vec<_> = lexer.tokenize(line)
-                .map_err(|error| PreprocError::lexer_error(line_number, error))?
-                // Apply semantics
+            let (tokens, errors) = lexer.tokenize(code, file, line_number);
+            let (warnings, errors): (Vec<_>, Vec<_>) = errors.into_iter().partition(LexerError::is_warning);
+            for warning in warnings {
+                report.warn(PreprocError::lexer_error(warning));
+            }
+            if !errors.is_empty() {
+                for error in errors {
+                    report.push(PreprocError::lexer_error(error));
+                }
+                continue;
+            }
+
+            let tokens: Vec<_> = tokens
+                // Apply semantics; definitions take priority over builtin words
                 .into_iter()
                 .map(|mut token| {
-                    if let Token::Word(WordToken::Direct(DirectWordToken { name, kind })) = &mut token {
-                        if let Some(value) = definitions.get(name) {
-                            _ = kind.insert(*value);
+                    if let TokenKind::Word(word) = &token.kind {
+                        let name = match word {
+                            WordToken::Direct(dw_token) => dw_token.name,
+                            WordToken::Builtin(_) => &code[token.span.start..token.span.end],
+                        };
+                        if let Some(definition) = definitions.get(name) {
+                            token.kind = TokenKind::Word(WordToken::defined(name, definition.kind));
                         }
                     }
                     token
                 })
                 .collect();
 
-            println!("tokens: {tokens:#?}");
+            log!(Verbosity::Tokens, "tokens: {tokens:#?}");
 
-            let syntax_tree = parse(tokens)
-                .map_err(|error| PreprocError::parse_error(line_number, error))?;
-            println!("syntax tree: {syntax_tree:#?}");
+            let syntax_tree = match parse(code, tokens) {
+                Ok(syntax_tree) => syntax_tree,
+                Err(error) => {
+                    report.push(PreprocError::parse_error(error));
+                    continue;
+                },
+            };
+            log!(Verbosity::Trees, "syntax tree: {syntax_tree:#?}");
+
+            if let Some(severity) = options.undefined_names.severity() {
+                for error in UndefinedNames::new(definitions, severity).check(&syntax_tree) {
+                    match options.undefined_names {
+                        LintLevel::Deny => report.push(PreprocError::lint_error(error)),
+                        _ => report.warn(PreprocError::lint_error(error)),
+                    }
+                }
+            }
+
+            for error in TypeChecker::new(definitions, code).check(&syntax_tree) {
+                report.warn(PreprocError::type_error(error));
+            }
 
             let tex = syntax_tree.to_tex();
-            // println!("syntax tree TeX: {tex}");
+
+            // Lines with diagnostics are rendered every time, so that their diagnostics are reported every time
+            if let Some(cache) = cache.as_deref_mut() {
+                if (report.errors.len(), report.warnings.len()) == diagnostic_count {
+                    cache.insert(line, context, tex.clone());
+                }
+            }
 
             // Append or create
             match content.last_mut() {
@@ -172,6 +455,10 @@ pub fn process_document<'doc>(document: &'doc str, template: &str) -> Result<Str
         }
     }
 
+    if !report.errors.is_empty() {
+        return Err(report);
+    }
+
     // Convert content structure into text
     let content_str = content
         .into_iter()
@@ -196,9 +483,93 @@ pub fn process_document<'doc>(document: &'doc str, template: &str) -> Result<Str
     // Insert meta variables into output
     for (key, value) in meta {
         let key_search = anchor_str(key);
-        println!("Assigning `{key_search}` anchors with \"{value}\"");
+        log!(Verbosity::Progress, "Assigning `{key_search}` anchors with \"{value}\"");
         output = output.replace(&key_search, value);
     }
 
-    Ok(output)
+    Ok((output, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// Process `document` into just its content, without a surrounding template.
+    fn process(document: &str, options: ProcessOptions) -> Result<(String, Report), Report> {
+        let mut sources = SourceMap::new(PathBuf::from("test.math"), document.to_string());
+        Processor::new().template(CONTENT_ANCHOR).options(options).process(&mut sources)
+    }
+
+    /// The kind of each warning for a document that processes without errors.
+    fn warnings(document: &str) -> Vec<LineErrorKind> {
+        let (_, report) = process(document, ProcessOptions::default())
+            .unwrap_or_else(|report| panic!("{document:?} should process: {:?}", report.errors));
+        report.warnings.into_iter()
+            .map(|warning| match warning {
+                PreprocError::LineError(error) => error.kind,
+                warning => panic!("{warning:?} should be a line error"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn shadowing_warnings() {
+        // Shadowing a definition from an outer heading is allowed
+        assert!(warnings("let x\n# Section\nfn x\nx\n").is_empty());
+        // Builtin variables are free to redefine
+        assert!(warnings("let phi\nphi\n").is_empty());
+
+        let kinds = warnings("fn x\n# Section\nlet y\nconst y\nconst e\n");
+        assert!(matches!(&kinds[..], [
+            LineErrorKind::Redefinition { name: y, kind: DefKind::Constant, previous_kind: DefKind::Variable, .. },
+            LineErrorKind::ShadowsBuiltin { name: e, kind: DefKind::Constant, builtin_kind: DefKind::Literal },
+        ] if y == "y" && e == "e"), "{kinds:?}");
+    }
+
+    /// The content of a document that processes without errors.
+    fn content(document: &str) -> String {
+        process(document, ProcessOptions::default())
+            .unwrap_or_else(|report| panic!("{document:?} should process: {:?}", report.errors))
+            .0
+    }
+
+    #[test]
+    fn definitions_with_subscripts() {
+        assert_eq!(content("let x1, v_max\nconst theta0\nx1 v_max theta0\n"), concat!(
+            "\\begin{gather*}\n",
+            r"\var{x_{1}} \var{v_{\text{max}}} \const{\theta_{0}}", "\n",
+            "\\end{gather*}",
+        ));
+        // `in` is only an operator on its own
+        assert_eq!(content("let index\nindex in S\n"), concat!(
+            "\\begin{gather*}\n",
+            r"{\var{index}}\stmt{\in}{{S}}", "\n",
+            "\\end{gather*}",
+        ));
+    }
+
+    #[test]
+    fn definitions_shadow_builtin_words() {
+        assert_eq!(content("fn theta\ntheta(x)\n"), concat!(
+            "\\begin{gather*}\n",
+            r"\fn{\theta}{{\br{\lparen}{{x}}{\rparen}}}", "\n",
+            "\\end{gather*}",
+        ));
+    }
+
+    #[test]
+    fn malformed_definitions() {
+        for line in ["let x_0", "let", "const 2", "fn f(x)", "let x, be in Real"] {
+            let report = process(line, ProcessOptions::default()).expect_err(line);
+            let kinds: Vec<_> = report.errors.into_iter()
+                .map(|error| match error {
+                    PreprocError::LineError(error) => error.kind,
+                    error => panic!("{error:?} should be a line error"),
+                })
+                .collect();
+            assert!(matches!(&kinds[..], [LineErrorKind::InvalidDefinition]), "{line:?}: {kinds:?}");
+        }
+    }
 }
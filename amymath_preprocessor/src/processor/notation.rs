@@ -0,0 +1,210 @@
+use std::sync::Arc;
+
+use regex::Captures;
+
+use crate::lexer::{*, notation::*};
+use super::{DefKind, error::NotationError};
+
+/// Matches a `notation` directive, capturing the pieces that [`NotationItem::from_captures`] interprets.
+pub const PATTERN: &str = r#"^notation\s+"(?<source>[^"\s]+)"(?:\s+"(?<close>[^"\s]+)")?\s+as\s+(?<tex>\S+)(?:\s+(?<rest>.+))?$"#;
+
+/// Notation from a `notation` directive, ready to be added to the [`Lexer`].
+#[derive(Debug)]
+pub enum NotationItem {
+    /// `notation "<~>" as \leftrightsquigarrow assertion precedence of =`
+    Operator(CustomOperator),
+    /// `notation "[|" "|]" as \llbracket \rrbracket`
+    Brackets(CustomBrackets),
+    /// `notation "grad" as \nabla function`
+    Word(CustomWord),
+}
+
+impl NotationItem {
+    /// Interpret the captures of a `notation` directive.
+    ///
+    /// `notation` is what the document has added so far, so that new operators can take their precedence from earlier ones.
+    pub fn from_captures(caps: &Captures<'_>, notation: &Notation) -> Result<Self, NotationError> {
+        let source = caps.name("source").unwrap().as_str();
+        let tex = caps.name("tex").unwrap().as_str();
+        let mut rest = caps.name("rest").map_or("", |rest| rest.as_str()).split_whitespace().peekable();
+
+        if let Some(close) = caps.name("close").map(|close| close.as_str()) {
+            let close_tex = rest.next().ok_or(NotationError::Malformed)?;
+            if rest.next().is_some() {
+                return Err(NotationError::Malformed);
+            }
+            if let Some(taken) = [source, close].into_iter().find(|token| notation.is_taken(token)) {
+                return Err(NotationError::Taken(taken.to_string()));
+            }
+            return Ok(Self::Brackets(CustomBrackets {
+                open: source.to_string(),
+                close: close.to_string(),
+                open_tex: tex.to_string(),
+                close_tex: close_tex.to_string(),
+            }));
+        }
+
+        match rest.next() {
+            Some(kind_str @ ("operation" | "assertion")) => {
+                let kind = if kind_str == "operation" { OpType::Operation } else { OpType::Assertion };
+                let fixity = match rest.peek() {
+                    Some(&"prefix") => Some(Fixity::Prefix),
+                    Some(&"infix") => Some(Fixity::Infix),
+                    Some(&"postfix") => Some(Fixity::Postfix),
+                    _ => None,
+                };
+                if fixity.is_some() {
+                    rest.next();
+                }
+                if (rest.next(), rest.next()) != (Some("precedence"), Some("of")) {
+                    return Err(NotationError::Malformed);
+                }
+                let like_str = rest.collect::<Vec<_>>().join(" ");
+                let like = match notation.operator(&like_str) {
+                    Some(op) => op.like.clone(),
+                    None => OperatorToken::try_from(&like_str)
+                        .ok_or(NotationError::UnknownOperator(like_str))?,
+                };
+                if notation.is_taken(source) {
+                    return Err(NotationError::Taken(source.to_string()));
+                }
+                Ok(Self::Operator(CustomOperator {
+                    source: source.to_string(),
+                    tex: tex.to_string(),
+                    kind,
+                    fixity: fixity.unwrap_or(Fixity::Infix),
+                    like,
+                }))
+            },
+
+            Some(kind_str @ ("literal" | "variable" | "constant" | "function")) => {
+                let kind = match kind_str {
+                    "literal"  => DefKind::Literal,
+                    "variable" => DefKind::Variable,
+                    "constant" => DefKind::Constant,
+                    _          => DefKind::Function,
+                };
+                if rest.next().is_some() {
+                    return Err(NotationError::Malformed);
+                }
+                if !source.chars().all(|c| c.is_ascii_alphabetic()) {
+                    return Err(NotationError::NotAWord(source.to_string()));
+                }
+                Ok(Self::Word(CustomWord {
+                    source: source.to_string(),
+                    tex: tex.to_string(),
+                    kind,
+                }))
+            },
+
+            other => Err(NotationError::UnknownKind(other.unwrap_or_default().to_string())),
+        }
+    }
+
+    /// Add the notation to the lexer for the rest of the document.
+    pub fn add_to(self, lexer: &mut Lexer) {
+        lexer.extend_notation(|notation| match self {
+            NotationItem::Operator(op) => notation.operators.push(Arc::new(op)),
+            NotationItem::Brackets(brackets) => notation.brackets.push(Arc::new(brackets)),
+            NotationItem::Word(word) => notation.words.push(word),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use super::*;
+    use crate::to_tex::ToTex;
+
+    /// Interpret a directive the way the processor does.
+    fn notation_item(line: &str, notation: &Notation) -> Result<NotationItem, NotationError> {
+        Regex::new(PATTERN).unwrap()
+            .captures(line)
+            .ok_or(NotationError::Malformed)
+            .and_then(|caps| NotationItem::from_captures(&caps, notation))
+    }
+
+    /// Each token on the line, as the text it was lexed from.
+    fn token_texts<'a>(lexer: &Lexer, line: &'a str) -> Vec<&'a str> {
+        lexer.tokenize(line, 0, 1).0.iter()
+            .map(|token| token.span.text(line))
+            .collect()
+    }
+
+    #[test]
+    fn operators() {
+        let notation = Notation::default();
+        let item = notation_item(r#"notation "<~>" as \leftrightsquigarrow assertion precedence of ="#, &notation);
+        let Ok(NotationItem::Operator(op)) = item else { panic!("{item:?} should be an operator") };
+        assert_eq!((op.source.as_str(), op.tex.as_str()), ("<~>", r"\leftrightsquigarrow"));
+        assert_eq!((op.kind, op.fixity, op.like), (OpType::Assertion, Fixity::Infix, OperatorToken::Eq));
+
+        let item = notation_item(r#"notation "~~" as \approx operation prefix precedence of -"#, &notation);
+        let Ok(NotationItem::Operator(op)) = item else { panic!("{item:?} should be an operator") };
+        assert_eq!((op.kind, op.fixity, op.like), (OpType::Operation, Fixity::Prefix, OperatorToken::Minus));
+    }
+
+    #[test]
+    fn operators_take_precedence_from_custom_operators() {
+        let mut lexer = Lexer::new();
+        notation_item(r#"notation "<~>" as \leftrightsquigarrow assertion precedence of ="#, lexer.notation())
+            .unwrap()
+            .add_to(&mut lexer);
+        let item = notation_item(r#"notation "<~~>" as \leadsto assertion precedence of <~>"#, lexer.notation());
+        assert!(matches!(item, Ok(NotationItem::Operator(CustomOperator { like: OperatorToken::Eq, .. }))), "{item:?}");
+    }
+
+    #[test]
+    fn brackets_and_words() {
+        let notation = Notation::default();
+        let item = notation_item(r#"notation "[|" "|]" as \llbracket \rrbracket"#, &notation);
+        let Ok(NotationItem::Brackets(brackets)) = item else { panic!("{item:?} should be brackets") };
+        assert_eq!(
+            [brackets.open, brackets.close, brackets.open_tex, brackets.close_tex],
+            ["[|", "|]", r"\llbracket", r"\rrbracket"],
+        );
+
+        let item = notation_item(r#"notation "grad" as \nabla function"#, &notation);
+        let Ok(NotationItem::Word(word)) = item else { panic!("{item:?} should be a word") };
+        assert_eq!((word.source.as_str(), word.tex.as_str(), word.kind), ("grad", r"\nabla", DefKind::Function));
+    }
+
+    #[test]
+    fn malformed_directives() {
+        let notation = Notation::default();
+        let error = |line| notation_item(line, &notation).map(|_| ()).unwrap_err();
+        assert!(matches!(error(r#"notation grad as \nabla function"#), NotationError::Malformed));
+        assert!(matches!(error(r#"notation "<~>" as \leadsto assertion"#), NotationError::Malformed));
+        assert!(matches!(error(r#"notation "<~>" as \leadsto assertion like ="#), NotationError::Malformed));
+        assert!(matches!(error(r#"notation "[|" "|]" as \llbracket"#), NotationError::Malformed));
+        assert!(matches!(error(r#"notation "[|" "|]" as \llbracket \rrbracket \relax"#), NotationError::Malformed));
+        assert!(matches!(error(r#"notation "grad" as \nabla function twice"#), NotationError::Malformed));
+        assert!(matches!(error(r#"notation "grad" as \nabla"#), NotationError::UnknownKind(kind) if kind.is_empty()));
+        assert!(matches!(error(r#"notation "grad" as \nabla vector"#), NotationError::UnknownKind(kind) if kind == "vector"));
+        assert!(matches!(error(r#"notation "<~>" as \leadsto assertion precedence of <~"#), NotationError::UnknownOperator(op) if op == "<~"));
+        assert!(matches!(error(r#"notation "+" as \oplus operation precedence of +"#), NotationError::Taken(token) if token == "+"));
+        assert!(matches!(error(r#"notation "<|" ")" as \lceil \rceil"#), NotationError::Taken(token) if token == ")"));
+        assert!(matches!(error(r#"notation "d2" as \partial variable"#), NotationError::NotAWord(token) if token == "d2"));
+    }
+
+    #[test]
+    fn notation_changes_later_lines() {
+        let mut lexer = Lexer::new();
+        assert_eq!(token_texts(&lexer, "a <~> b"), ["a", "<", "~", ">", "b"]);
+        assert_eq!(token_texts(&lexer, "[|x|]"), ["[", "|", "x", "|", "]"]);
+
+        for line in [
+            r#"notation "<~>" as \leftrightsquigarrow assertion precedence of ="#,
+            r#"notation "[|" "|]" as \llbracket \rrbracket"#,
+            r#"notation "grad" as \nabla function"#,
+        ] {
+            notation_item(line, lexer.notation()).unwrap().add_to(&mut lexer);
+        }
+        assert_eq!(token_texts(&lexer, "a <~> b"), ["a", "<~>", "b"]);
+        assert_eq!(token_texts(&lexer, "[|x|]"), ["[|", "x", "|]"]);
+        let (tokens, _) = lexer.tokenize("grad f", 0, 1);
+        assert_eq!(tokens[0].clone().to_tex(), r"\fn{\nabla}");
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use crate::stack::Stack;
+use super::definition::Definition;
+
+/// The definitions made under one heading.
+#[derive(Debug, Default)]
+struct Scope<'doc> {
+    /// The depth of the heading that opened the scope. The document itself is depth 0.
+    depth: usize,
+    definitions: HashMap<&'doc str, Definition<'doc>>,
+}
+
+/// The definitions visible at the current point in the document.
+///
+/// Each heading opens a scope that lasts until the next heading of the same or lesser depth,
+/// so a definition under `## Trig` ends at the next section or chapter.
+/// Definitions made before the first heading, or marked `global`, last for the whole document.
+#[derive(Default)]
+pub struct Scopes<'doc> {
+    global: Scope<'doc>,
+    headings: Stack<Scope<'doc>>,
+    /// Every visible definition, kept until a definition is added or removed.
+    visible: Option<HashMap<&'doc str, Definition<'doc>>>,
+}
+
+impl<'doc> Scopes<'doc> {
+    /// Construct a scope stack with only the document scope.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Close the scopes of any headings that the new heading is a sibling or parent of, and open a scope for the new heading.
+    pub fn enter_heading(&mut self, depth: usize) {
+        while self.headings.top().is_some_and(|scope| scope.depth >= depth) {
+            if self.headings.pop().is_some_and(|scope| !scope.definitions.is_empty()) {
+                self.visible = None;
+            }
+        }
+        self.headings.push(Scope { depth, definitions: HashMap::new() });
+    }
+
+    /// Define a name in the innermost scope, or in the document scope if `global`.
+    ///
+    /// Returns the definition the name had in that same scope, if any.
+    pub fn define(&mut self, name: &'doc str, definition: Definition<'doc>, global: bool) -> Option<Definition<'doc>> {
+        self.visible = None;
+        let scope = match self.headings.top_mut() {
+            Some(scope) if !global => scope,
+            _ => &mut self.global,
+        };
+        scope.definitions.insert(name, definition)
+    }
+
+    /// Remove the innermost definition of a name, so that any outer definition or builtin word shows through.
+    ///
+    /// Returns the definition that was removed, if there was one.
+    pub fn undefine(&mut self, name: &str) -> Option<Definition<'doc>> {
+        self.visible = None;
+        self.headings.iter_mut()
+            .chain(std::iter::once(&mut self.global))
+            .find_map(|scope| scope.definitions.remove(name))
+    }
+
+    /// Every visible definition. Names defined in inner scopes shadow the same names in outer scopes.
+    ///
+    /// The map is only rebuilt after definitions change, so lines of math in between share it.
+    pub fn visible(&mut self) -> &HashMap<&'doc str, Definition<'doc>> {
+        let (global, headings) = (&self.global, &self.headings);
+        self.visible.get_or_insert_with(|| {
+            let mut visible = global.definitions.clone();
+            let headings: Vec<_> = headings.iter().collect();
+            // Outermost first, so that inner definitions overwrite outer ones
+            for scope in headings.into_iter().rev() {
+                visible.extend(scope.definitions.iter().map(|(name, definition)| (*name, *definition)));
+            }
+            visible
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Span, processor::DefKind};
+
+    fn definition(kind: DefKind) -> Definition<'static> {
+        Definition { kind, set: None, span: Span::default() }
+    }
+
+    /// The visible names, sorted.
+    fn visible_names<'doc>(scopes: &mut Scopes<'doc>) -> Vec<&'doc str> {
+        let mut names: Vec<_> = scopes.visible().keys().copied().collect();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn headings_close_scopes() {
+        let mut scopes = Scopes::new();
+        scopes.define("a", definition(DefKind::Variable), false);
+        scopes.enter_heading(1);
+        scopes.define("b", definition(DefKind::Variable), false);
+        scopes.enter_heading(2);
+        scopes.define("c", definition(DefKind::Variable), false);
+        assert_eq!(visible_names(&mut scopes), ["a", "b", "c"]);
+
+        // A sibling closes the previous subsection
+        scopes.enter_heading(2);
+        assert_eq!(visible_names(&mut scopes), ["a", "b"]);
+        // A parent closes everything under the previous section
+        scopes.enter_heading(1);
+        assert_eq!(visible_names(&mut scopes), ["a"]);
+    }
+
+    #[test]
+    fn global_definitions_outlast_headings() {
+        let mut scopes = Scopes::new();
+        scopes.enter_heading(2);
+        scopes.define("g", definition(DefKind::Constant), true);
+        scopes.define("l", definition(DefKind::Variable), false);
+        scopes.enter_heading(1);
+        assert_eq!(visible_names(&mut scopes), ["g"]);
+    }
+
+    #[test]
+    fn undefine_reveals_outer_definition() {
+        let mut scopes = Scopes::new();
+        scopes.define("x", definition(DefKind::Variable), false);
+        scopes.enter_heading(1);
+        scopes.define("x", definition(DefKind::Function), false);
+        assert_eq!(scopes.visible()["x"].kind, DefKind::Function);
+
+        assert!(scopes.undefine("x").is_some());
+        assert_eq!(scopes.visible()["x"].kind, DefKind::Variable);
+        assert!(scopes.undefine("x").is_some());
+        assert!(visible_names(&mut scopes).is_empty());
+        assert!(scopes.undefine("x").is_none());
+    }
+
+    #[test]
+    fn define_returns_definition_in_same_scope() {
+        let mut scopes = Scopes::new();
+        assert!(scopes.define("x", definition(DefKind::Variable), false).is_none());
+        assert_eq!(scopes.define("x", definition(DefKind::Function), false).map(|previous| previous.kind), Some(DefKind::Variable));
+        // Shadowing an outer definition isn't redefining it
+        scopes.enter_heading(1);
+        assert!(scopes.define("x", definition(DefKind::Constant), false).is_none());
+    }
+
+    #[test]
+    fn visible_map_follows_changes() {
+        let mut scopes = Scopes::new();
+        scopes.define("a", definition(DefKind::Variable), false);
+        scopes.enter_heading(1);
+        scopes.define("b", definition(DefKind::Variable), false);
+        assert_eq!(visible_names(&mut scopes), ["a", "b"]);
+
+        scopes.undefine("a");
+        assert_eq!(visible_names(&mut scopes), ["b"]);
+        scopes.define("c", definition(DefKind::Variable), true);
+        assert_eq!(visible_names(&mut scopes), ["b", "c"]);
+        scopes.enter_heading(1);
+        assert_eq!(visible_names(&mut scopes), ["c"]);
+        // Leaving an empty scope keeps the map as it is
+        scopes.enter_heading(1);
+        assert_eq!(visible_names(&mut scopes), ["c"]);
+    }
+}
@@ -0,0 +1,76 @@
+use std::error::Error;
+
+use crate::diagnostic::{Diagnostic, ToDiagnostic};
+use crate::lexer::span::Span;
+use super::ValueType;
+
+/// A subexpression whose type doesn't fit where it is used.
+#[derive(Debug)]
+pub enum TypeError {
+    /// An operand has a type the operator can't be applied to, like `+` on a proposition.
+    Mismatch {
+        /// How the operator is written in the source, like `+` or `sum`.
+        op_str: String,
+        /// The type the operator takes.
+        expected: ValueType,
+        /// The type of the operand.
+        found: ValueType,
+        /// The region of the operand.
+        span: Span,
+    },
+
+    /// The two sides of a comparison have different types, like `x = A` where `A` is a set.
+    Incomparable {
+        /// How the operator is written in the source, like `=`.
+        op_str: String,
+        /// The type of the left hand side.
+        lhs: ValueType,
+        /// The region of the left hand side.
+        lhs_span: Span,
+        /// The type of the right hand side.
+        rhs: ValueType,
+        /// The region of the right hand side.
+        rhs_span: Span,
+    },
+}
+
+impl TypeError {
+    /// The region of the source the error is reported at.
+    pub fn span(&self) -> Span {
+        match self {
+            TypeError::Mismatch { span, .. } => *span,
+            TypeError::Incomparable { rhs_span, .. } => *rhs_span,
+        }
+    }
+}
+
+impl std::fmt::Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypeError::Mismatch { op_str, expected, found, span }
+                => write!(f, "`{op_str}` expects {expected} but was given {found} at {span}."),
+
+            TypeError::Incomparable { op_str, lhs, rhs, rhs_span, .. }
+                => write!(f, "`{op_str}` compares {lhs} with {rhs} at {rhs_span}."),
+        }
+    }
+}
+
+impl ToDiagnostic for TypeError {
+    fn to_diagnostic(&self) -> Diagnostic {
+        match self {
+            TypeError::Mismatch { op_str, expected, found, span }
+                => Diagnostic::warning("Mismatched types")
+                    .with_primary(*span, format!("expected {expected}, found {found}"))
+                    .with_help(format!("`{op_str}` expects {expected} here")),
+
+            TypeError::Incomparable { op_str, lhs, lhs_span, rhs, rhs_span }
+                => Diagnostic::warning(format!("`{op_str}` between {lhs} and {rhs}"))
+                    .with_primary(*rhs_span, format!("this is {rhs}"))
+                    .with_secondary(*lhs_span, format!("this is {lhs}"))
+                    .with_help("both sides of a comparison should be the same type"),
+        }
+    }
+}
+
+impl Error for TypeError {}
@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+
+use crate::lexer::{*, builtin::BuiltinWordToken};
+use crate::parser::syntax_tree::{SyntaxNode, SyntaxTree};
+use super::{DefKind, definition::*};
+
+/// `semantics` error module.
+pub mod error;
+use error::*;
+
+/// What kind of value an expression evaluates to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    /// A number, like `2` or `x` where `x` is in `Real`.
+    Number,
+    /// A set, like `Real` or `{1, 2}`.
+    Set,
+    /// A statement that is true or false, like `a = b`.
+    Proposition,
+    /// A function that has not been applied to arguments, like `f` in `f = g`.
+    Function,
+}
+
+impl ValueType {
+    /// The type of the elements of a set. `None` if the elements could be anything.
+    pub fn of_element(set: SetKind<'_>) -> Option<Self> {
+        match set {
+            | SetKind::Natural
+            | SetKind::Integer
+            | SetKind::Rational
+            | SetKind::Real
+            | SetKind::Complex => Some(Self::Number),
+            SetKind::Boolean => Some(Self::Proposition),
+            SetKind::User(_) => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ValueType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValueType::Number => write!(f, "a number"),
+            ValueType::Set => write!(f, "a set"),
+            ValueType::Proposition => write!(f, "a proposition"),
+            ValueType::Function => write!(f, "a function"),
+        }
+    }
+}
+
+/// Infers the type of each subexpression of a syntax tree and collects the places where types don't fit.
+///
+/// Subexpressions whose type can't be known, like a variable defined without a set, are never reported.
+pub struct TypeChecker<'def, 'doc> {
+    definitions: &'def HashMap<&'doc str, Definition<'doc>>,
+    /// The line the checked trees were parsed from, for quoting operators as they were written.
+    line: &'doc str,
+    errors: Vec<TypeError>,
+}
+
+impl<'def, 'doc> TypeChecker<'def, 'doc> {
+    /// Construct a type checker for trees parsed from `line` that looks names up in `definitions`.
+    pub fn new(definitions: &'def HashMap<&'doc str, Definition<'doc>>, line: &'doc str) -> Self {
+        Self { definitions, line, errors: Vec::new() }
+    }
+
+    /// Check every subexpression of the tree.
+    pub fn check(mut self, tree: &SyntaxTree<'_>) -> Vec<TypeError> {
+        self.tree_type(tree);
+        self.errors
+    }
+
+    /// Infer the type of a node without reporting anything, for nodes that have already been checked.
+    fn infer(&self, node: &SyntaxNode<'_>) -> Option<ValueType> {
+        TypeChecker::new(self.definitions, self.line).node_type(node)
+    }
+
+    /// Report the operand if its type is known and isn't `expected`.
+    fn expect(&mut self, op_str: &str, expected: ValueType, found: Option<ValueType>, span: Span) {
+        if let Some(found) = found.filter(|found| *found != expected) {
+            self.errors.push(TypeError::Mismatch { op_str: op_str.to_string(), expected, found, span });
+        }
+    }
+
+    /// The type of a name written in the document.
+    fn name_type(&self, name: &str) -> Option<ValueType> {
+        if let Some(definition) = self.definitions.get(name) {
+            return match definition.kind {
+                DefKind::Function => Some(ValueType::Function),
+                _ => definition.set.and_then(ValueType::of_element),
+            };
+        }
+        is_set_name(self.definitions, name).then_some(ValueType::Set)
+    }
+
+    fn word_type(&self, word: &WordToken<'_>) -> Option<ValueType> {
+        match word {
+            WordToken::Direct(dw_token) => self.name_type(dw_token.name).or(match dw_token.kind {
+                // Words added with `notation`
+                Some(DefKind::Literal) => Some(ValueType::Number),
+                Some(DefKind::Function) => Some(ValueType::Function),
+                _ => None,
+            }),
+            WordToken::Builtin(BuiltinWordToken::VarNothing) => Some(ValueType::Set),
+            WordToken::Builtin(bw_token) => match bw_token.kind() {
+                DefKind::Function => Some(ValueType::Function),
+                _ => Some(ValueType::Number),
+            },
+        }
+    }
+
+    /// The type of a tree's content. Adjacent numbers, like `2 x`, are multiplied.
+    fn tree_type(&mut self, tree: &SyntaxTree<'_>) -> Option<ValueType> {
+        let types: Vec<_> = tree.nodes.iter()
+            .map(|node| self.node_type(node))
+            .collect();
+        match types[..] {
+            [node_type] => node_type,
+            [_, _, ..] if types.iter().all(|node_type| *node_type == Some(ValueType::Number)) => Some(ValueType::Number),
+            _ => None,
+        }
+    }
+
+    fn node_type(&mut self, node: &SyntaxNode<'_>) -> Option<ValueType> {
+        match node {
+            SyntaxNode::Token(token) => match &token.kind {
+                TokenKind::Number(_) => Some(ValueType::Number),
+                TokenKind::Word(word) => self.word_type(word),
+                _ => None,
+            },
+
+            SyntaxNode::Operator { lhs, op, rhs, op_span, .. } => self.operator_type(lhs, op.clone(), *op_span, rhs),
+
+            SyntaxNode::Apply { function, subscript, superscript, args, .. } => {
+                for tree in [subscript.as_ref(), superscript.as_ref(), Some(args)].into_iter().flatten() {
+                    self.tree_type(tree);
+                }
+                match function {
+                    WordToken::Direct(dw_token) => self.definitions.get(dw_token.name)
+                        .and_then(|definition| definition.set)
+                        .and_then(ValueType::of_element),
+                    WordToken::Builtin(_) => Some(ValueType::Number),
+                }
+            },
+
+            SyntaxNode::BigOp { op, index, body, op_span, .. } => {
+                if let Some(index) = index {
+                    self.tree_type(index);
+                }
+                let operand = match op {
+                    BigOpToken::Sqrt | BigOpToken::Sum | BigOpToken::Prod => ValueType::Number,
+                    BigOpToken::Union | BigOpToken::Intersection => ValueType::Set,
+                };
+                let body_type = self.tree_type(body);
+                self.expect(op_span.text(self.line), operand, body_type, body.span);
+                Some(operand)
+            },
+
+            SyntaxNode::Group { open, inner, .. } => {
+                let inner_type = self.tree_type(inner);
+                match open {
+                    BracketKind::Paren | BracketKind::Brack | BracketKind::Blank => inner_type,
+                    BracketKind::Brace => Some(ValueType::Set),
+                    BracketKind::Vert | BracketKind::VVert | BracketKind::Floor | BracketKind::Ceil => Some(ValueType::Number),
+                    BracketKind::Angle | BracketKind::AAngle | BracketKind::Custom(_) => None,
+                }
+            },
+        }
+    }
+
+    /// Check the arguments of an operator and infer the type of its result.
+    ///
+    /// Operators without a specific rule are assumed to produce a proposition if they are an
+    /// [`OpType::Assertion`], and to produce an unknown type if they are an [`OpType::Operation`].
+    fn operator_type(&mut self, lhs: &[SyntaxNode<'_>], op: OperatorToken, op_span: Span, rhs: &[SyntaxNode<'_>]) -> Option<ValueType> {
+        let mut args: Vec<_> = lhs.iter().chain(rhs)
+            .map(|node| (self.node_type(node), node.span()))
+            .collect();
+
+        // In `a < b < c`, the outer `<` compares `b` and `c` rather than `a < b` and `c`
+        if let (Assoc::Chain, [SyntaxNode::Operator { op: inner_op, rhs: inner_rhs, .. }]) = (op.assoc(), lhs) {
+            match &inner_rhs[..] {
+                [shared] if inner_op.precedence() == op.precedence() => args[0] = (self.infer(shared), shared.span()),
+                _ => (),
+            }
+        }
+
+        let op_str = op_span.text(self.line);
+        let expect_all = |this: &mut Self, expected: ValueType| {
+            for (found, span) in &args {
+                this.expect(op_str, expected, *found, *span);
+            }
+            Some(expected)
+        };
+
+        use OperatorToken as Op;
+        match op {
+            Op::Subscript | Op::Prime => args[0].0,
+
+            Op::Superscript => {
+                let (power, power_span) = args[1];
+                self.expect(op_str, ValueType::Number, power, power_span);
+                args[0].0
+            },
+
+            | Op::Factorial | Op::CDot | Op::Frac | Op::Choose
+            | Op::Pm | Op::Mp | Op::Plus | Op::Minus
+                => expect_all(self, ValueType::Number),
+
+            Op::Gt | Op::Ge | Op::Lt | Op::Le => {
+                expect_all(self, ValueType::Number);
+                Some(ValueType::Proposition)
+            },
+
+            Op::Range | Op::RangeExclusive => {
+                expect_all(self, ValueType::Number);
+                Some(ValueType::Set)
+            },
+
+            Op::In => {
+                let (set, set_span) = args[1];
+                self.expect(op_str, ValueType::Set, set, set_span);
+                Some(ValueType::Proposition)
+            },
+
+            Op::Eq | Op::Ne | Op::Equiv | Op::NEquiv | Op::Sim => {
+                if let [(Some(lhs), lhs_span), (Some(rhs), rhs_span)] = args[..] {
+                    if lhs != rhs {
+                        self.errors.push(TypeError::Incomparable { op_str: op_str.to_string(), lhs, lhs_span, rhs, rhs_span });
+                    }
+                }
+                Some(ValueType::Proposition)
+            },
+
+            Op::Intersection | Op::Union | Op::Setminus
+                => expect_all(self, ValueType::Set),
+
+            | Op::Not | Op::Wedge | Op::And | Op::Nand | Op::Xor | Op::Xnor | Op::Vee | Op::Or | Op::Nor
+            | Op::Implies | Op::Impliedby | Op::Iff | Op::Therefore | Op::Because
+                => expect_all(self, ValueType::Proposition),
+
+            Op::MapsTo | Op::MapsFrom => Some(ValueType::Function),
+
+            _ => match op.kind() {
+                OpType::Assertion => Some(ValueType::Proposition),
+                OpType::Operation => None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    /// The operator each type error on the line quotes, with `a` defined in `Real`.
+    fn quoted_operators(line: &str) -> Vec<String> {
+        let definitions = HashMap::from([
+            ("a", Definition { kind: DefKind::Variable, set: Some(SetKind::Real), span: Span::default() }),
+        ]);
+        let lexer = Lexer::new();
+        let (tokens, _) = lexer.tokenize(line, 0, 1);
+        let tree = parse(line, tokens).unwrap_or_else(|error| panic!("{line:?} should parse: {error}"));
+        TypeChecker::new(&definitions, line).check(&tree)
+            .into_iter()
+            .map(|error| match error {
+                TypeError::Mismatch { op_str, .. } | TypeError::Incomparable { op_str, .. } => op_str,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn quotes_operators_as_written() {
+        assert_eq!(quoted_operators("Real = a"), ["="]);
+        assert_eq!(quoted_operators("Real == a"), ["=="]);
+        assert_eq!(quoted_operators("a ..= Real"), ["..="]);
+        assert_eq!(quoted_operators("a .. Real"), [".."]);
+        assert_eq!(quoted_operators("union[i] a"), ["union"]);
+    }
+}
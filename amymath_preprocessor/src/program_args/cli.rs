@@ -0,0 +1,214 @@
+macro_rules! commands {
+    {
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $(
+                $(#[$cmd_meta:meta])*
+                $cmd:ident => $cmd_str:literal => $help:literal,
+            )*
+        }
+    } => {
+        $(#[$meta])*
+        $vis enum $name {
+            $(
+                $(#[$cmd_meta])*
+                $cmd,
+            )*
+        }
+
+        impl $name {
+            /// Every command, in the order they are listed in the help text.
+            pub const ALL: &'static [Self] = &[$(Self::$cmd),*];
+
+            /// Try to construct a command from its name. If `None`, the argument is not a command.
+            pub fn try_from(arg: &str) -> Option<Self> {
+                match arg {
+                    $($cmd_str => Some(Self::$cmd),)*
+                    _ => None,
+                }
+            }
+
+            /// The name the command is invoked with.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(Self::$cmd => $cmd_str,)*
+                }
+            }
+
+            /// A one-line description of the command.
+            pub fn help(&self) -> &'static str {
+                match self {
+                    $(Self::$cmd => $help,)*
+                }
+            }
+        }
+    };
+}
+
+macro_rules! cli_options {
+    {
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $(
+                $(#[$opt_meta:meta])*
+                $opt:ident => $long:literal $(| $short:literal)? $(<$value:literal>)? [$($cmd:ident),*] => $help:literal,
+            )*
+        }
+    } => {
+        $(#[$meta])*
+        $vis enum $name {
+            $(
+                $(#[$opt_meta])*
+                $opt,
+            )*
+        }
+
+        impl $name {
+            /// Every option, in the order they are listed in the help text.
+            pub const ALL: &'static [Self] = &[$(Self::$opt),*];
+
+            /// Try to construct an option from an argument like `-o` or `--output`. If `None`, the option doesn't exist.
+            pub fn try_from(arg: &str) -> Option<Self> {
+                match arg {
+                    $($long $(| $short)? => Some(Self::$opt),)*
+                    _ => None,
+                }
+            }
+
+            /// The short form of the option, like `-o`, if it has one.
+            pub fn short(&self) -> Option<&'static str> {
+                match self {
+                    $(Self::$opt => [$($short)?].first().copied(),)*
+                }
+            }
+
+            /// The long form of the option, like `--output`.
+            pub fn long(&self) -> &'static str {
+                match self {
+                    $(Self::$opt => $long,)*
+                }
+            }
+
+            /// What the value following the option is, if the option takes one.
+            pub fn value_name(&self) -> Option<&'static str> {
+                match self {
+                    $(Self::$opt => [$($value)?].first().copied(),)*
+                }
+            }
+
+            /// Whether `command` accepts this option.
+            pub fn accepted_by(&self, command: Command) -> bool {
+                match self {
+                    $(Self::$opt => [$(Command::$cmd),*].contains(&command),)*
+                }
+            }
+
+            /// A one-line description of the option.
+            pub fn help(&self) -> &'static str {
+                match self {
+                    $(Self::$opt => $help,)*
+                }
+            }
+        }
+    };
+}
+
+commands!{
+    /// What the program has been asked to do with the source document.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub enum Command {
+        /// Convert the document into LaTeX.
+        #[default]
+        Build => "build" => "Convert the document into LaTeX (the default when no command is given)",
+        /// Report errors and warnings without writing anything.
+        Check => "check" => "Report errors and warnings without writing any output",
+        /// Tidy the layout of the document.
+        Fmt => "fmt" => "Tidy the layout of the document, rewriting it in place unless -o is given",
+        /// Rebuild whenever the document changes.
+        Watch => "watch" => "Rebuild the document whenever it, a file it imports, or the template changes",
+        /// Show the tokens or syntax trees of every line of math.
+        Dump => "dump" => "Show the tokens or syntax trees of every line of math on stderr",
+    }
+}
+
+cli_options!{
+    /// A flag or key accepted on the command line.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum CliOption {
+        /// `-o`, `--output`
+        Output => "--output" | "-o" <"PATH"> [Build, Fmt, Watch] => "Where to write the output, or - for stdout",
+        /// `-t`, `--template`
+        Template => "--template" | "-t" <"PATH"> [Build, Check, Watch] => "The LaTeX template to put the document in",
+        /// `--emit-declarations`
+        EmitDeclarations => "--emit-declarations" [Build, Check, Watch] => "Render definitions that name a set as math",
+        /// `--warn-undefined`
+        WarnUndefined => "--warn-undefined" [Build, Check, Watch, Dump] => "Warn about names used without being defined",
+        /// `--deny-undefined`
+        DenyUndefined => "--deny-undefined" [Build, Check, Watch, Dump] => "Treat names used without being defined as errors",
+        /// `--error-limit`
+        ErrorLimit => "--error-limit" <"N"> [Build, Check, Watch, Dump] => "Stop after N errors, or 0 for no limit",
+        /// `--cache`
+        Cache => "--cache" <"PATH"> [Build, Check, Watch] => "Keep the TeX of unchanged lines in PATH between runs",
+        /// `--unknown-chars`
+        UnknownChars => "--unknown-chars" <"deny|warn|passthrough"> [Build, Check, Watch, Dump]
+            => "What to do with characters that aren't part of the language (default: deny); passthrough escapes TeX's special characters",
+        /// `--stage`
+        Stage => "--stage" <"tokens|trees"> [Dump] => "What to show for each line (default: trees)",
+        /// `-q`, `--quiet`
+        Quiet => "--quiet" | "-q" [Build, Check, Fmt, Watch, Dump] => "Only report errors",
+        /// `-v`, `--verbose`
+        Verbose => "--verbose" | "-v" [Build, Check, Fmt, Watch, Dump] => "Report progress; -vv adds tokens and -vvv adds syntax trees",
+        /// `-h`, `--help`
+        Help => "--help" | "-h" [Build, Check, Fmt, Watch, Dump] => "Show this help",
+        /// `-V`, `--version`
+        Version => "--version" | "-V" [Build, Check, Fmt, Watch, Dump] => "Show the version",
+    }
+}
+
+/// The name the program is run as.
+const PROGRAM_NAME: &str = env!("CARGO_PKG_NAME");
+
+/// The version line shown by `--version`.
+pub fn version() -> String {
+    format!("{PROGRAM_NAME} {}", env!("CARGO_PKG_VERSION"))
+}
+
+/// The help text for a command, or for the whole program if `None`.
+pub fn help(command: Option<Command>) -> String {
+    let mut text = format!("{}\nPreprocessor for converting AmyMath (.math) documents into LaTeX.\n\n", version());
+
+    match command {
+        Some(command) => text += &format!("{}\n\nUsage: {PROGRAM_NAME} {} <SOURCE> [OPTIONS]\n", command.help(), command.name()),
+        None => {
+            text += &format!("Usage: {PROGRAM_NAME} [COMMAND] <SOURCE> [OPTIONS]\n\nCommands:\n");
+            let width = Command::ALL.iter().map(|command| command.name().len()).max().unwrap_or(0);
+            for command in Command::ALL {
+                text += &format!("  {:width$}  {}\n", command.name(), command.help());
+            }
+        },
+    }
+
+    text += "\nArguments:\n  <SOURCE>  The .math document, or - for stdin\n\nOptions:\n";
+    let usages = CliOption::ALL.iter()
+        .filter(|option| command.is_none_or(|command| option.accepted_by(command)))
+        .map(|option| {
+            let mut usage = match option.short() {
+                Some(short) => format!("{short}, {}", option.long()),
+                None => format!("    {}", option.long()),
+            };
+            if let Some(value) = option.value_name() {
+                usage += &format!(" <{value}>");
+            }
+            (usage, option.help())
+        })
+        .collect::<Vec<_>>();
+    let width = usages.iter().map(|(usage, _)| usage.len()).max().unwrap_or(0);
+    for (usage, help) in usages {
+        text += &format!("  {usage:width$}  {help}\n");
+    }
+
+    if command.is_none() {
+        text += &format!("\nRun `{PROGRAM_NAME} <COMMAND> --help` for the options a command accepts.\n");
+    }
+    text
+}
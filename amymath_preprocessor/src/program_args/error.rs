@@ -12,8 +12,26 @@ pub enum ArgsError {
         /// The valueless key that was given.
         key: &'static str,
     },
+    /// A value was given that the key does not accept.
+    InvalidValue {
+        /// The key the value was given to.
+        key: &'static str,
+        /// The rejected value.
+        value: String,
+    },
     /// An unexpected argument was provided.
-    UnknownArg(String)
+    UnknownArg(String),
+    /// An option was given to a command that doesn't use it.
+    NotAccepted {
+        /// The long form of the option.
+        option: &'static str,
+        /// The name of the command.
+        command: &'static str,
+    },
+    /// The watch command was given stdin as the source, which can't be watched for changes.
+    CannotWatchStdin,
+    /// The default output file already exists and was not generated by this program.
+    WouldOverwrite(std::path::PathBuf),
 }
 
 impl std::fmt::Display for ArgsError {
@@ -25,8 +43,16 @@ impl std::fmt::Display for ArgsError {
                 => write!(f, "Missing argument for source file"),
             ArgsError::MissingKVPValue { key }
                 => write!(f, "Missing value for {key} argument"),
+            ArgsError::InvalidValue { key, value }
+                => write!(f, "Invalid value for {key} argument: \"{value}\""),
             ArgsError::UnknownArg(arg)
                 => write!(f, "Unrecognized argument: \"{arg}\""),
+            ArgsError::NotAccepted { option, command }
+                => write!(f, "The {command} command does not accept {option}"),
+            ArgsError::CannotWatchStdin
+                => write!(f, "The watch command needs a source file, not stdin"),
+            ArgsError::WouldOverwrite(path)
+                => write!(f, "{} already exists and was not generated from a document; choose the output with -o to replace it", path.display()),
         }
     }
 }
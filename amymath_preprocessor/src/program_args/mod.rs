@@ -1,55 +1,303 @@
-use std::{fs::{read_to_string, File}, path::PathBuf};
+use std::{fs::read_to_string, path::PathBuf};
+
+use amymath::{LintLevel, ProcessOptions, UnknownChars, DEFAULT_TEMPLATE, Verbosity, VERBOSITY_VAR};
 
 /// `program_args` error module.
 pub mod error;
+/// Commands and options accepted on the command line, and the help text generated from them.
+pub mod cli;
 use error::ArgsError;
+pub use cli::{Command, CliOption};
+
+/// The first line of every output file, marking it as safe to overwrite.
+pub const GENERATED_MARKER: &str = "% Generated by amymath_preprocessor. Edit the source document instead; changes here will be overwritten.";
 
-const DEFAULT_TEMPLATE: &str = include_str!("../template.sty");
+/// The argument that stands for stdin as the source or stdout as the output.
+const STD_STREAM_ARG: &str = "-";
+
+/// Where the processed document is written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Output {
+    /// A file, which is created or replaced.
+    File(PathBuf),
+    /// Standard output, so that the tool can be used in a pipeline.
+    Stdout,
+}
 
 /// Program arguments.
 pub struct ProgramArgs {
+    /// What to do with the source document.
+    pub command: Command,
     /// Source document as a string.
     pub source: String,
-    /// Path to the file the output should be stored to.
-    pub out_path: PathBuf,
+    /// Path to the source document. `None` if it was read from stdin.
+    pub source_path: Option<PathBuf>,
+    /// Where the output should be written.
+    pub output: Output,
     /// The template document as a string.
     pub template: String,
+    /// Path to the template document. `None` if the default template is used.
+    pub template_path: Option<PathBuf>,
+    /// Settings for the preprocessor.
+    pub options: ProcessOptions,
+    /// Where to keep rendered lines between runs, if anywhere.
+    pub cache_path: Option<PathBuf>,
+    /// How much to report while processing.
+    /// Taken from `-q`/`-v`/`-vv`, or from the `AMYMATH_LOG` environment variable when neither is given.
+    pub verbosity: Verbosity,
+    /// What [`Command::Dump`] shows: [`Verbosity::Tokens`] or [`Verbosity::Trees`].
+    pub dump_stage: Verbosity,
+}
+
+/// What the command line asks for.
+pub enum Invocation {
+    /// Run a command on a document.
+    Run(ProgramArgs),
+    /// Show the help text for a command, or for the whole program if `None`.
+    Help(Option<Command>),
+    /// Show the version.
+    Version,
 }
 
 impl ProgramArgs {
     /// Extract information from command line.
-    pub fn try_from(root: PathBuf, mut args: std::env::Args) -> Result<Self, ArgsError> {
-        let _exe_path = args.next();
+    ///
+    /// The first argument is the path of the executable. `root` is the directory relative paths are resolved from.
+    pub fn try_from(root: PathBuf, args: impl IntoIterator<Item = String>) -> Result<Invocation, ArgsError> {
+        let mut args = args.into_iter().skip(1).peekable();
 
-        // Mandatory
+        let explicit_command = args.peek().and_then(|arg| Command::try_from(arg));
+        if explicit_command.is_some() {
+            args.next();
+        }
+        let command = explicit_command.unwrap_or_default();
 
-        let src_path = root.join(args.next().ok_or(ArgsError::MissingSource)?);
-        println!("source path: {src_path:#?} exists? {}", File::open(&src_path).is_ok());
+        let mut src_arg = None;
+        let mut out_arg = None;
+        let mut template_path = None;
+        let mut cache_path = None;
+        let mut options = ProcessOptions::default();
+        let mut verbosity_flag = None;
+        let mut dump_stage = Verbosity::Trees;
 
-        // Optional
+        while let Some(arg) = args.next() {
+            let (option, verbose_steps) = match arg.strip_prefix("-v") {
+                // `-vv` and `-vvv` are repeated `-v`s
+                Some(vs) if vs.chars().all(|c| c == 'v') => (CliOption::Verbose, arg.len() - 1),
+                _ if arg.starts_with('-') && arg != STD_STREAM_ARG => {
+                    (CliOption::try_from(&arg).ok_or(ArgsError::UnknownArg(arg))?, 1)
+                },
+                _ => {
+                    if src_arg.is_some() {
+                        return Err(ArgsError::UnknownArg(arg));
+                    }
+                    src_arg = Some(arg);
+                    continue;
+                },
+            };
 
-        let mut result = ProgramArgs {
-            source: read_to_string(&src_path)?,
-            out_path: src_path.with_file_name("output").with_extension("tex"),
-            template: DEFAULT_TEMPLATE.to_string(),
-        };
+            if !option.accepted_by(command) {
+                return Err(ArgsError::NotAccepted { option: option.long(), command: command.name() });
+            }
+            let mut value = || match option.value_name() {
+                Some(_) => args.next().ok_or(ArgsError::MissingKVPValue { key: option.long() }),
+                None => Ok(String::new()),
+            };
 
-        while let Some(arg) = args.next() {
-            match arg.as_str() {
-                "-o" | "--output" => {
-                    result.out_path = root.join(args.next().ok_or(ArgsError::MissingKVPValue { key: "output" })?);
+            match option {
+                CliOption::Output => out_arg = Some(value()?),
+                CliOption::Template => template_path = Some(root.join(value()?)),
+                CliOption::Cache => cache_path = Some(root.join(value()?)),
+                CliOption::EmitDeclarations => options.emit_declarations = true,
+                CliOption::WarnUndefined => options.undefined_names = LintLevel::Warn,
+                CliOption::DenyUndefined => options.undefined_names = LintLevel::Deny,
+                CliOption::ErrorLimit => {
+                    let value = value()?;
+                    let limit = value.parse::<usize>()
+                        .map_err(|_| ArgsError::InvalidValue { key: option.long(), value })?;
+                    // A limit of 0 means no limit
+                    options.error_limit = (limit != 0).then_some(limit);
                 },
-                "-t" | "--template" => {
-                    let template_path = root.join(args.next().ok_or(ArgsError::MissingKVPValue { key: "template" })?);
-                    result.template = read_to_string(&template_path)?;
+                CliOption::UnknownChars => {
+                    let value = value()?;
+                    options.unknown_chars = match value.as_str() {
+                        "deny" => UnknownChars::Deny,
+                        "warn" => UnknownChars::Warn,
+                        "passthrough" => UnknownChars::Passthrough,
+                        _ => return Err(ArgsError::InvalidValue { key: option.long(), value }),
+                    };
                 },
-                _ => return Err(ArgsError::UnknownArg(arg)),
+                CliOption::Stage => {
+                    let value = value()?;
+                    dump_stage = match value.as_str() {
+                        "tokens" => Verbosity::Tokens,
+                        "trees" => Verbosity::Trees,
+                        _ => return Err(ArgsError::InvalidValue { key: option.long(), value }),
+                    };
+                },
+                CliOption::Quiet => verbosity_flag = Some(Verbosity::Quiet),
+                CliOption::Verbose => {
+                    verbosity_flag = Some(verbosity_flag.unwrap_or(Verbosity::Normal).increased(verbose_steps));
+                },
+                CliOption::Help => return Ok(Invocation::Help(explicit_command)),
+                CliOption::Version => return Ok(Invocation::Version),
             }
         }
 
-        println!("output path: {:#?}", result.out_path);
-        // println!("template document:\n{}", result.template);
+        let src_arg = src_arg.ok_or(ArgsError::MissingSource)?;
+        let (source, source_path) = if src_arg == STD_STREAM_ARG {
+            if command == Command::Watch {
+                return Err(ArgsError::CannotWatchStdin);
+            }
+            (std::io::read_to_string(std::io::stdin())?, None)
+        } else {
+            let src_path = root.join(src_arg);
+            (read_to_string(&src_path)?, Some(src_path))
+        };
+
+        let output = match (out_arg.as_deref(), &source_path) {
+            (Some(STD_STREAM_ARG), _) | (None, None) => Output::Stdout,
+            (Some(out_arg), _) => Output::File(root.join(out_arg)),
+            // Formatting replaces the document itself
+            (None, Some(src_path)) if command == Command::Fmt => Output::File(src_path.clone()),
+            (None, Some(src_path)) => {
+                let out_path = src_path.with_file_name("output").with_extension("tex");
+                // Only an output file that was asked for by name may replace a file we didn't generate
+                let is_generated = || read_to_string(&out_path)
+                    .is_ok_and(|existing| existing.starts_with(GENERATED_MARKER));
+                if matches!(command, Command::Build | Command::Watch) && out_path.exists() && !is_generated() {
+                    return Err(ArgsError::WouldOverwrite(out_path));
+                }
+                Output::File(out_path)
+            },
+        };
+
+        let template = match &template_path {
+            Some(template_path) => read_to_string(template_path)?,
+            None => DEFAULT_TEMPLATE.to_string(),
+        };
+
+        let verbosity = match verbosity_flag {
+            Some(verbosity) => verbosity,
+            None => match std::env::var(VERBOSITY_VAR) {
+                Ok(value) => Verbosity::try_from(&value)
+                    .ok_or(ArgsError::InvalidValue { key: VERBOSITY_VAR, value })?,
+                Err(_) => Verbosity::default(),
+            },
+        };
+
+        Ok(Invocation::Run(ProgramArgs {
+            command,
+            source,
+            source_path,
+            output,
+            template,
+            template_path,
+            options,
+            cache_path,
+            verbosity,
+            dump_stage,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The executable, which comes before the arguments.
+    const PROGRAM_NAME: &str = "amymath_preprocessor";
+
+    /// A directory holding a `doc.math` to pass as the source, unique to `test`.
+    fn root(test: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("amymath_args_{}_{test}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("doc.math"), "let x\n").unwrap();
+        root
+    }
+
+    /// Parse a command line, which is given without the executable.
+    fn invoke(root: &std::path::Path, args: &str) -> Result<Invocation, ArgsError> {
+        let args = std::iter::once(PROGRAM_NAME).chain(args.split_whitespace()).map(str::to_string);
+        ProgramArgs::try_from(root.to_path_buf(), args)
+    }
+
+    /// Parse a command line that should run a command.
+    fn run(root: &std::path::Path, args: &str) -> ProgramArgs {
+        match invoke(root, args) {
+            Ok(Invocation::Run(args)) => args,
+            Ok(_) => panic!("`{args}` should run a command"),
+            Err(error) => panic!("`{args}` should run a command: {error}"),
+        }
+    }
+
+    /// Parse a command line that should be rejected.
+    fn reject(root: &std::path::Path, args: &str) -> ArgsError {
+        match invoke(root, args) {
+            Err(error) => error,
+            Ok(_) => panic!("`{args}` should be rejected"),
+        }
+    }
+
+    #[test]
+    fn command_is_optional() {
+        let root = root("command_is_optional");
+        let args = run(&root, "doc.math -o out.tex");
+        assert_eq!(args.command, Command::Build);
+        assert_eq!(args.source, "let x\n");
+        assert_eq!(args.source_path, Some(root.join("doc.math")));
+        assert_eq!(args.output, Output::File(root.join("out.tex")));
+
+        assert_eq!(run(&root, "check doc.math").command, Command::Check);
+        // Without a command, `fmt` is the name of the source document
+        assert!(matches!(reject(&root, "doc.math fmt"), ArgsError::UnknownArg(arg) if arg == "fmt"));
+        assert!(matches!(invoke(&root, "check --help"), Ok(Invocation::Help(Some(Command::Check)))));
+        assert!(matches!(invoke(&root, "--help"), Ok(Invocation::Help(None))));
+    }
+
+    #[test]
+    fn dash_is_a_standard_stream() {
+        let root = root("dash_is_a_standard_stream");
+        assert_eq!(run(&root, "doc.math -o -").output, Output::Stdout);
+        assert_eq!(run(&root, "fmt doc.math").output, Output::File(root.join("doc.math")));
+        assert!(matches!(reject(&root, "watch -"), ArgsError::CannotWatchStdin));
+    }
+
+    #[test]
+    fn options_belong_to_commands() {
+        let root = root("options_belong_to_commands");
+        assert!(CliOption::Output.accepted_by(Command::Fmt));
+        assert!(!CliOption::Output.accepted_by(Command::Check));
+        assert!(CliOption::Stage.accepted_by(Command::Dump));
+        assert!(!CliOption::Stage.accepted_by(Command::Build));
+
+        let error = reject(&root, "check doc.math -o out.tex");
+        assert!(matches!(error, ArgsError::NotAccepted { option: "--output", command: "check" }), "{error}");
+        assert_eq!(run(&root, "dump doc.math --stage tokens").dump_stage, Verbosity::Tokens);
+    }
+
+    #[test]
+    fn verbose_flags_stack() {
+        let root = root("verbose_flags_stack");
+        assert_eq!(run(&root, "check doc.math -q").verbosity, Verbosity::Quiet);
+        assert_eq!(run(&root, "check doc.math -v").verbosity, Verbosity::Progress);
+        assert_eq!(run(&root, "check doc.math -vv").verbosity, Verbosity::Tokens);
+        assert_eq!(run(&root, "check doc.math -vvv").verbosity, Verbosity::Trees);
+        assert_eq!(run(&root, "check doc.math -v -vv").verbosity, Verbosity::Trees);
+        assert_eq!(run(&root, "check doc.math -vvvvv").verbosity, Verbosity::Trees);
+        assert!(matches!(reject(&root, "check doc.math -vx"), ArgsError::UnknownArg(arg) if arg == "-vx"));
+    }
+
+    #[test]
+    fn option_values() {
+        let root = root("option_values");
+        assert_eq!(run(&root, "check doc.math --error-limit 3").options.error_limit, Some(3));
+        assert_eq!(run(&root, "check doc.math --error-limit 0").options.error_limit, None);
 
-        Ok(result)
+        let error = reject(&root, "check doc.math --error-limit");
+        assert!(matches!(error, ArgsError::MissingKVPValue { key: "--error-limit" }), "{error}");
+        let error = reject(&root, "check doc.math --error-limit many");
+        assert!(matches!(error, ArgsError::InvalidValue { key: "--error-limit", ref value } if value == "many"), "{error}");
+        assert!(matches!(reject(&root, "check"), ArgsError::MissingSource));
     }
 }
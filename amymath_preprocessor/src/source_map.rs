@@ -0,0 +1,89 @@
+use std::{collections::HashMap, path::{Path, PathBuf}};
+
+use crate::lexer::span::{FileId, Span};
+
+/// A file that is part of the document.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    /// Where the file was read from. Canonicalized when possible, so that the same file is never loaded twice.
+    pub path: PathBuf,
+    /// The name shown when reporting errors in the file.
+    pub name: String,
+    /// The text of the file.
+    pub content: String,
+    /// The `@import` directive that brought in this file. `None` for the file given to the program.
+    pub imported_at: Option<Span>,
+}
+
+/// Every file that makes up a document: the file given to the program, and the files it imports.
+#[derive(Debug, Clone)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+    /// Which file each `@import` line refers to, by the file and line number of the directive.
+    imports: HashMap<(FileId, usize), FileId>,
+}
+
+impl SourceMap {
+    /// Construct a source map containing only the file given to the program.
+    pub fn new(path: PathBuf, content: String) -> Self {
+        let name = path.display().to_string();
+        let path = path.canonicalize().unwrap_or(path);
+        Self {
+            files: vec![SourceFile { path, name, content, imported_at: None }],
+            imports: HashMap::new(),
+        }
+    }
+
+    /// Construct a source map for a document read from stdin. Its imports are found relative to `dir`.
+    pub fn from_stdin(dir: PathBuf, content: String) -> Self {
+        Self {
+            files: vec![SourceFile { path: dir.join("<stdin>"), name: "<stdin>".to_string(), content, imported_at: None }],
+            imports: HashMap::new(),
+        }
+    }
+
+    /// The file with the given ID.
+    pub fn file(&self, id: FileId) -> &SourceFile {
+        &self.files[id]
+    }
+
+    /// Every file, in the order they were loaded.
+    pub fn files(&self) -> &[SourceFile] {
+        &self.files
+    }
+
+    /// The ID of the file that was read from `path`, if it has been loaded.
+    pub fn find(&self, path: &Path) -> Option<FileId> {
+        self.files.iter().position(|file| file.path == path)
+    }
+
+    /// Add a file brought in by the `@import` directive at `imported_at`.
+    pub fn add(&mut self, path: PathBuf, content: String, imported_at: Span) -> FileId {
+        let name = path.display().to_string();
+        self.files.push(SourceFile { path, name, content, imported_at: Some(imported_at) });
+        self.files.len() - 1
+    }
+
+    /// Record that the `@import` directive on `line` of `file` refers to `target`.
+    pub fn resolve_import(&mut self, file: FileId, line: usize, target: FileId) {
+        self.imports.insert((file, line), target);
+    }
+
+    /// The file that the `@import` directive on `line` of `file` refers to, if it could be loaded.
+    pub fn import(&self, file: FileId, line: usize) -> Option<FileId> {
+        self.imports.get(&(file, line)).copied()
+    }
+
+    /// A line of a file, starting from 1. Empty if the line doesn't exist.
+    pub fn line(&self, file: FileId, line_number: usize) -> &str {
+        line_number.checked_sub(1)
+            .and_then(|i| self.files.get(file)?.content.lines().nth(i))
+            .unwrap_or("")
+    }
+
+    /// The `@import` directives leading from the file given to the program to `file`, innermost first.
+    pub fn import_chain(&self, file: FileId) -> Vec<Span> {
+        std::iter::successors(self.files.get(file).and_then(|file| file.imported_at), |span| self.files[span.file].imported_at)
+            .collect()
+    }
+}
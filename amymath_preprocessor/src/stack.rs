@@ -4,9 +4,15 @@ use std::collections::LinkedList;
 
 /// A collection adapter for [`LinkedList`].
 ///
-/// Allow pushing/popping from the top in O(1) time. All other elements are hidden.
+/// Allow pushing/popping from the top in O(1) time. All other elements can only be reached through [`Stack::iter`] and [`Stack::iter_mut`].
 pub struct Stack<T>(LinkedList<T>);
 
+impl<T> Default for Stack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> Stack<T> {
     /// Construct an empty stack.
     pub fn new() -> Self {
@@ -37,4 +43,14 @@ impl<T> Stack<T> {
     pub fn top_mut(&mut self) -> Option<&mut T> {
         self.0.front_mut()
     }
+
+    /// Iterate over the items from the top of the stack to the bottom.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.0.iter()
+    }
+
+    /// Iterate mutably over the items from the top of the stack to the bottom.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.0.iter_mut()
+    }
 }
@@ -1,4 +1,7 @@
 /// The structure can be converted to LaTeX.
+///
+/// Saved line caches are keyed on the source of the renderer, so a file with new implementations may need adding to the
+/// list in [`LineCache`](crate::LineCache)'s module.
 pub trait ToTex {
     /// Convert the object into LaTeX, consuming it.
     fn to_tex(self) -> String;
@@ -0,0 +1,91 @@
+use std::{fs::read_to_string, path::PathBuf, process::ExitCode, thread::sleep, time::{Duration, SystemTime}};
+
+use amymath::Verbosity;
+use crate::{process, source_map, write_output, load_cache, save_cache, program_args::{Output, ProgramArgs, GENERATED_MARKER}};
+
+/// How long to wait between checks for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+/// When each watched file was last modified, to compare against later.
+struct Snapshot(Vec<(PathBuf, Option<SystemTime>)>);
+
+impl Snapshot {
+    /// Record the modification times of `paths`. Files that can't be read are recorded as `None`.
+    fn take(paths: Vec<PathBuf>) -> Self {
+        Self(paths.into_iter()
+            .map(|path| {
+                let modified = path.metadata().and_then(|metadata| metadata.modified()).ok();
+                (path, modified)
+            })
+            .collect())
+    }
+
+    /// The first file that was modified, created, or removed since the snapshot was taken.
+    fn changed(&self) -> Option<&PathBuf> {
+        self.0.iter()
+            .find(|(path, modified)| path.metadata().and_then(|metadata| metadata.modified()).ok() != *modified)
+            .map(|(path, _)| path)
+    }
+}
+
+/// Rebuild the document every time the source, a file it imports, or the template changes. Runs until interrupted.
+///
+/// Lines of math that didn't change between rebuilds are not rendered again.
+/// The output is only rewritten when it differs from what is already there, so that tools watching the `.tex` aren't
+/// triggered by rebuilds that changed nothing.
+pub fn watch(args: &ProgramArgs) -> ExitCode {
+    let source_path = args.source_path.clone()
+        .expect("stdin is rejected for watch when parsing arguments");
+    let mut last_output = match &args.output {
+        Output::File(out_path) => read_to_string(out_path).ok(),
+        Output::Stdout => None,
+    };
+
+    // Kept between rebuilds even without `--cache`, so that only edited lines are rendered again
+    let mut cache = load_cache(args).unwrap_or_default();
+
+    loop {
+        let mut watched = vec![source_path.clone()];
+        watched.extend(args.template_path.clone());
+
+        let source = read_to_string(&source_path);
+        let template = args.template_path.as_ref()
+            .map_or(Ok(args.template.clone()), read_to_string);
+
+        match (source, template) {
+            (Err(error), _) | (_, Err(error)) => eprintln!("IO Error: {error}"),
+            (Ok(source), Ok(template)) => {
+                let mut sources = source_map(args, source);
+                let output = process(args, &mut sources, &template, Some(&mut cache));
+                save_cache(args, Some(&mut cache));
+                // Imported files are known once the document has been processed
+                watched.extend(sources.files().iter().skip(1).map(|file| file.path.clone()));
+
+                if let Some(output) = output {
+                    let written = match &args.output {
+                        Output::File(_) => format!("{GENERATED_MARKER}\n{output}"),
+                        Output::Stdout => output.clone(),
+                    };
+                    if last_output.as_ref() == Some(&written) {
+                        log!(Verbosity::Normal, "[watch] output unchanged");
+                    } else if write_output(&args.output, &output, true) == ExitCode::SUCCESS {
+                        if let Output::File(out_path) = &args.output {
+                            log!(Verbosity::Normal, "[watch] wrote {}", out_path.display());
+                        }
+                        last_output = Some(written);
+                    }
+                }
+            },
+        }
+
+        log!(Verbosity::Progress, "[watch] watching {} files", watched.len());
+        let snapshot = Snapshot::take(watched);
+        let changed = loop {
+            sleep(POLL_INTERVAL);
+            if let Some(path) = snapshot.changed() {
+                break path;
+            }
+        };
+        log!(Verbosity::Normal, "[watch] {} changed, rebuilding", changed.display());
+    }
+}